@@ -0,0 +1,336 @@
+//! Times every sorter against the standard library's sorts, and the red
+//! black tree against `BTreeMap`.
+//!
+//! Run with `cargo bench --bench bench`, passing options after `--`:
+//!
+//! - `--csv` writes CSV rather than markdown tables
+//! - `--sizes 100,10000` sets the slice and map sizes
+//! - `--filter Quick` only runs benchmarks whose name contains `Quick`
+//! - `--budget 200` sets the milliseconds spent on each measurement
+
+use std::collections::BTreeMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use chonky_structures::data_structures::red_black_tree::RedBlackTree;
+use chonky_structures::sorting::inputs::{self, Distribution};
+use chonky_structures::sorting::*;
+
+const SEED: u64 = 0x5eed;
+
+/// Builds an input of the given length.
+type Generator = Box<dyn Fn(usize) -> Vec<u64>>;
+
+/// Sorters that are quadratic on average are only run up to this size.
+const QUADRATIC_LIMIT: usize = 10_000;
+
+struct Options {
+    csv: bool,
+    sizes: Vec<usize>,
+    filter: Option<String>,
+    budget: Duration,
+}
+
+impl Options {
+    fn parse() -> Self {
+        let mut options = Options {
+            csv: false,
+            sizes: vec![100, 1_000, 10_000, 100_000],
+            filter: None,
+            budget: Duration::from_millis(100),
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--csv" => options.csv = true,
+                "--sizes" => {
+                    let sizes = args.next().expect("--sizes takes a list of sizes");
+                    options.sizes = sizes
+                        .split(',')
+                        .map(|s| s.trim().parse().expect("sizes are integers"))
+                        .collect();
+                }
+                "--filter" => options.filter = args.next(),
+                "--budget" => {
+                    let millis = args.next().expect("--budget takes milliseconds");
+                    options.budget =
+                        Duration::from_millis(millis.parse().expect("budget is an integer"));
+                }
+                // cargo passes `--bench`, ignore it and anything else
+                _ => {}
+            }
+        }
+        options
+    }
+
+    fn wants(&self, name: &str) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|f| name.contains(f.as_str()))
+    }
+}
+
+/// Runs `f` on fresh copies of `input` until the budget is spent, and
+/// returns the median time of a run.
+fn time<F>(input: &[u64], budget: Duration, mut f: F) -> Duration
+where
+    F: FnMut(&mut [u64]),
+{
+    let mut times = Vec::new();
+    let start = Instant::now();
+    while times.len() < 3 || (start.elapsed() < budget && times.len() < 10_000) {
+        let mut list = input.to_vec();
+        let before = Instant::now();
+        f(black_box(&mut list));
+        times.push(before.elapsed());
+        black_box(&list);
+    }
+    times.sort_unstable();
+    times[times.len() / 2]
+}
+
+/// Measurements in one table: a row per name, a column per size.
+struct Table {
+    title: String,
+    sizes: Vec<usize>,
+    rows: Vec<(String, Vec<Option<Duration>>)>,
+}
+
+impl Table {
+    fn new(title: String, sizes: &[usize]) -> Self {
+        Table {
+            title,
+            sizes: sizes.to_vec(),
+            rows: Vec::new(),
+        }
+    }
+
+    fn print(&self, csv: bool) {
+        if csv {
+            for (name, times) in &self.rows {
+                for (size, time) in self.sizes.iter().zip(times) {
+                    if let Some(time) = time {
+                        println!("{},{name},{size},{}", self.title, time.as_nanos());
+                    }
+                }
+            }
+            return;
+        }
+
+        println!("### {}\n", self.title);
+        print!("| |");
+        for size in &self.sizes {
+            print!(" {size} |");
+        }
+        print!("\n|---|");
+        for _ in &self.sizes {
+            print!("---:|");
+        }
+        println!();
+        for (name, times) in &self.rows {
+            print!("| {name} |");
+            for time in times {
+                match time {
+                    Some(time) => print!(" {} |", format_duration(*time)),
+                    None => print!(" |"),
+                }
+            }
+            println!();
+        }
+        println!();
+    }
+}
+
+fn format_duration(time: Duration) -> String {
+    let nanos = time.as_nanos();
+    if nanos < 10_000 {
+        format!("{nanos} ns")
+    } else if nanos < 10_000_000 {
+        format!("{:.1} µs", nanos as f64 / 1e3)
+    } else {
+        format!("{:.1} ms", nanos as f64 / 1e6)
+    }
+}
+
+fn bench_sorters(options: &Options) {
+    let shell: Vec<_> = GapSequence::ALL
+        .into_iter()
+        .map(|gaps| (format!("ShellSort ({gaps:?})"), ShellSort { gaps }))
+        .collect();
+    let parallel_merge = ParallelMergeSort::default();
+    let parallel_quick = ParallelQuickSort::default();
+    let mut sorters: Vec<(String, &dyn Sorter<u64>)> = vec![
+        ("BubbleSort".into(), &BubbleSort),
+        ("SelectionSort".into(), &SelectionSort),
+        ("InsertionSort".into(), &InsertionSort),
+        ("HeapSort".into(), &HeapSort),
+        ("BottomUpHeapSort".into(), &BottomUpHeapSort),
+        ("SmoothSort".into(), &SmoothSort),
+        ("WeakHeapSort".into(), &WeakHeapSort),
+        ("QuickSort".into(), &QuickSort {}),
+        ("MergeSort".into(), &MergeSort {}),
+        ("BottomUpMergeSort".into(), &BottomUpMergeSort {}),
+        ("TimSort".into(), &TimSort),
+        ("BlockMergeSort".into(), &BlockMergeSort),
+        ("AutoSort".into(), &AutoSort),
+        ("ParallelMergeSort".into(), &parallel_merge),
+        ("ParallelQuickSort".into(), &parallel_quick),
+    ];
+    sorters.extend(
+        shell
+            .iter()
+            .map(|(name, s)| (name.clone(), s as &dyn Sorter<u64>)),
+    );
+
+    let mut distributions: Vec<(String, Generator)> = Distribution::ALL
+        .into_iter()
+        .map(|d| {
+            (
+                format!("{d:?}"),
+                Box::new(move |n| d.generate(n, SEED)) as Box<_>,
+            )
+        })
+        .collect();
+    distributions.push((
+        "Zipf".into(),
+        Box::new(|n| inputs::zipf(n, n as u64, 1.0, SEED)),
+    ));
+
+    for (distribution, generate) in &distributions {
+        let mut table = Table::new(format!("sort {distribution}"), &options.sizes);
+        let inputs: Vec<_> = options.sizes.iter().map(|&n| generate(n)).collect();
+        let mut row = |name: &str, f: &mut dyn FnMut(&mut [u64]), quadratic: bool| {
+            if !options.wants(name) {
+                return;
+            }
+            let times = inputs
+                .iter()
+                .map(|input| {
+                    (!quadratic || input.len() <= QUADRATIC_LIMIT)
+                        .then(|| time(input, options.budget, &mut *f))
+                })
+                .collect();
+            table.rows.push((name.to_string(), times));
+        };
+
+        row("slice::sort", &mut |s| s.sort(), false);
+        row("slice::sort_unstable", &mut |s| s.sort_unstable(), false);
+        for (name, sorter) in &sorters {
+            let quadratic = sorter.properties().average == Complexity::Quadratic;
+            row(name, &mut |s| sorter.sort(s), quadratic);
+        }
+        row("LsdRadixSort", &mut |s| LsdRadixSort.sort(s), false);
+        row("MsdRadixSort", &mut |s| MsdRadixSort.sort(s), false);
+        table.print(options.csv);
+    }
+}
+
+/// The operations timed on both maps.
+trait Map: Default {
+    fn insert(&mut self, k: u64, v: u64);
+    fn get(&self, k: &u64) -> Option<&u64>;
+    fn remove(&mut self, k: &u64) -> Option<u64>;
+    fn sum(&self) -> u64;
+}
+
+impl Map for BTreeMap<u64, u64> {
+    fn insert(&mut self, k: u64, v: u64) {
+        BTreeMap::insert(self, k, v);
+    }
+
+    fn get(&self, k: &u64) -> Option<&u64> {
+        BTreeMap::get(self, k)
+    }
+
+    fn remove(&mut self, k: &u64) -> Option<u64> {
+        BTreeMap::remove(self, k)
+    }
+
+    fn sum(&self) -> u64 {
+        self.iter().map(|(k, v)| k ^ v).sum()
+    }
+}
+
+impl Map for RedBlackTree<u64, u64> {
+    fn insert(&mut self, k: u64, v: u64) {
+        RedBlackTree::insert(self, k, v);
+    }
+
+    fn get(&self, k: &u64) -> Option<&u64> {
+        RedBlackTree::get(self, k)
+    }
+
+    fn remove(&mut self, k: &u64) -> Option<u64> {
+        RedBlackTree::remove(self, k)
+    }
+
+    fn sum(&self) -> u64 {
+        self.iter().map(|(k, v)| k ^ v).sum()
+    }
+}
+
+fn bench_map<M: Map>(name: &str, table: &mut [Table; 4], options: &Options) {
+    if !options.wants(name) {
+        return;
+    }
+    let mut times: [Vec<Option<Duration>>; 4] = Default::default();
+    for &n in &options.sizes {
+        let keys = inputs::random(n, SEED);
+        let mut full = M::default();
+        for &k in &keys {
+            full.insert(k, k);
+        }
+
+        times[0].push(Some(time(&keys, options.budget, |keys| {
+            let mut map = M::default();
+            for &k in keys.iter() {
+                map.insert(k, k);
+            }
+            black_box(map);
+        })));
+        times[1].push(Some(time(&keys, options.budget, |keys| {
+            for k in keys.iter() {
+                black_box(full.get(k));
+            }
+        })));
+        // removing needs a full map each run, so time building it too and
+        // subtract the time of just building it
+        let remove = time(&keys, options.budget, |keys| {
+            let mut map = M::default();
+            for &k in keys.iter() {
+                map.insert(k, k);
+            }
+            for k in keys.iter() {
+                black_box(map.remove(k));
+            }
+        });
+        times[2].push(Some(
+            remove.saturating_sub(times[0].last().unwrap().unwrap()),
+        ));
+        times[3].push(Some(time(&[], options.budget, |_| {
+            black_box(full.sum());
+        })));
+    }
+    for (table, times) in table.iter_mut().zip(times) {
+        table.rows.push((name.to_string(), times));
+    }
+}
+
+fn bench_maps(options: &Options) {
+    let mut tables = ["insert", "get", "remove", "iter"]
+        .map(|op| Table::new(format!("map {op}"), &options.sizes));
+    bench_map::<BTreeMap<u64, u64>>("BTreeMap", &mut tables, options);
+    bench_map::<RedBlackTree<u64, u64>>("RedBlackTree", &mut tables, options);
+    for table in tables.iter().filter(|t| !t.rows.is_empty()) {
+        table.print(options.csv);
+    }
+}
+
+fn main() {
+    let options = Options::parse();
+    if options.csv {
+        println!("benchmark,name,size,nanos");
+    }
+    bench_sorters(&options);
+    bench_maps(&options);
+}
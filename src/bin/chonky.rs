@@ -0,0 +1,482 @@
+//! `chonky`, for sorting files with any of the sorters and trying out the
+//! red-black tree from the command line. Run `chonky help` for the options.
+//!
+//! Exits with 2 on bad arguments and 1 on bad input or I/O errors.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process::ExitCode;
+
+use chonky_structures::data_structures::red_black_tree::RedBlackTree;
+use chonky_structures::sorting::instrument::{Instrumented, SortStats};
+use chonky_structures::sorting::{merge, registry};
+
+const USAGE: &str = "\
+usage: chonky sort [options] [file]
+       chonky tree [--print | --dot] [script]
+
+Reads from standard input when no file is given, or the file is `-`.
+
+sort prints the lines of the input in order.
+    -a, --algorithm NAME  the sorter to use, `auto` by default
+    -n, --numeric         compare the keys as numbers
+    -r, --reverse         sort in descending order
+    -u, --unique          only print the first of each run of equal keys
+    -k, --key N           compare the Nth whitespace separated field rather
+                          than the whole line, counting from 1
+    -s, --stats           print how much work the sort did to stderr
+
+tree runs a script against a red-black tree of integer keys, a command per
+line. Blank lines and lines starting with `#` are skipped.
+    insert KEY [VALUE]    insert or replace an entry
+    remove KEY            remove an entry
+    get KEY               print an entry's value
+    range LOW HIGH        print the entries with keys in LOW..=HIGH
+    len                   print the number of entries
+    print                 draw the tree
+    dot                   draw the tree in Graphviz's DOT language
+    --print, --dot        draw the tree after every command
+";
+
+enum Error {
+    /// The arguments don't make sense, exits with 2.
+    Usage(String),
+    /// The input doesn't make sense, exits with 1.
+    Input(String),
+    Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(message) | Error::Input(message) => f.write_str(message),
+            Error::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+fn usage(message: impl Into<String>) -> Error {
+    Error::Usage(message.into())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut out = BufWriter::new(io::stdout().lock());
+    let result = run(&args, &mut out).and_then(|()| Ok(out.flush()?));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        // the reader went away, like `chonky sort | head`
+        Err(Error::Io(e)) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(e @ Error::Usage(_)) => {
+            eprintln!("chonky: {e}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+        Err(e) => {
+            eprintln!("chonky: {e}");
+            ExitCode::from(1)
+        }
+    }
+}
+
+fn run(args: &[String], out: &mut dyn Write) -> Result<(), Error> {
+    match args.first().map(String::as_str) {
+        Some("sort") => {
+            let options = SortOptions::parse(&args[1..])?;
+            let input = open(options.path.as_deref())?;
+            if let Some(stats) = sort(&options, input, out)? {
+                eprintln!(
+                    "comparisons: {}, swaps: {}, writes: {}",
+                    stats.comparisons, stats.swaps, stats.writes
+                );
+            }
+            Ok(())
+        }
+        Some("tree") => {
+            let options = TreeOptions::parse(&args[1..])?;
+            let input = open(options.path.as_deref())?;
+            tree(&options, input, out)
+        }
+        Some("help" | "-h" | "--help") => Ok(out.write_all(USAGE.as_bytes())?),
+        Some(command) => Err(usage(format!("unknown command `{command}`"))),
+        None => Err(usage("missing command")),
+    }
+}
+
+fn open(path: Option<&str>) -> Result<Box<dyn BufRead>, Error> {
+    match path {
+        None | Some("-") => Ok(Box::new(io::stdin().lock())),
+        Some(path) => match File::open(path) {
+            Ok(file) => Ok(Box::new(BufReader::new(file))),
+            Err(e) => Err(Error::Input(format!("can't open {path}: {e}"))),
+        },
+    }
+}
+
+/// Sets `path` to `arg`, unless it's already set or `arg` looks like an
+/// option.
+fn positional(path: &mut Option<String>, arg: &str) -> Result<(), Error> {
+    if arg.starts_with('-') && arg != "-" {
+        Err(usage(format!("unknown option `{arg}`")))
+    } else if path.is_some() {
+        Err(usage("only one input file can be given"))
+    } else {
+        *path = Some(arg.to_string());
+        Ok(())
+    }
+}
+
+struct SortOptions {
+    algorithm: String,
+    numeric: bool,
+    reverse: bool,
+    unique: bool,
+    /// The field to compare, counting from 0.
+    key: Option<usize>,
+    stats: bool,
+    path: Option<String>,
+}
+
+impl SortOptions {
+    fn parse(args: &[String]) -> Result<Self, Error> {
+        let mut options = SortOptions {
+            algorithm: "auto".to_string(),
+            numeric: false,
+            reverse: false,
+            unique: false,
+            key: None,
+            stats: false,
+            path: None,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-a" | "--algorithm" => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| usage("--algorithm takes a name"))?;
+                    options.algorithm = name.clone();
+                }
+                "-n" | "--numeric" => options.numeric = true,
+                "-r" | "--reverse" => options.reverse = true,
+                "-u" | "--unique" => options.unique = true,
+                "-k" | "--key" => {
+                    let field = args.next().ok_or_else(|| usage("--key takes a field"))?;
+                    match field.parse::<usize>() {
+                        Ok(field) if field > 0 => options.key = Some(field - 1),
+                        _ => return Err(usage(format!("`{field}` isn't a field number"))),
+                    }
+                }
+                "-s" | "--stats" => options.stats = true,
+                arg => positional(&mut options.path, arg)?,
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// A line of input, and the part of it that's compared.
+struct Record {
+    line: String,
+    key: (usize, usize),
+    number: f64,
+}
+
+impl Record {
+    fn key(&self) -> &str {
+        &self.line[self.key.0..self.key.1]
+    }
+}
+
+/// Sorts the lines of `input` into `out`, and returns the work it took if
+/// `options` asks for it.
+fn sort(
+    options: &SortOptions,
+    input: impl BufRead,
+    out: &mut dyn Write,
+) -> Result<Option<SortStats>, Error> {
+    let Some(sorter) = registry::get::<Record>(&options.algorithm) else {
+        let names: Vec<_> = registry::entries::<Record>()
+            .iter()
+            .map(|entry| entry.name)
+            .collect();
+        return Err(usage(format!(
+            "unknown algorithm `{}`, pick one of {}",
+            options.algorithm,
+            names.join(", ")
+        )));
+    };
+
+    let mut records = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        let key = match options.key {
+            Some(n) => match line.split_whitespace().nth(n) {
+                Some(field) => {
+                    let start = field.as_ptr() as usize - line.as_ptr() as usize;
+                    (start, start + field.len())
+                }
+                None => (line.len(), line.len()),
+            },
+            None => (0, line.len()),
+        };
+        let mut record = Record {
+            line,
+            key,
+            number: 0.0,
+        };
+        if options.numeric {
+            let key = record.key().trim();
+            record.number = key
+                .parse()
+                .map_err(|_| Error::Input(format!("line {}: `{key}` isn't a number", i + 1)))?;
+        }
+        records.push(record);
+    }
+
+    let mut compare = |a: &Record, b: &Record| {
+        let order = if options.numeric {
+            a.number.total_cmp(&b.number)
+        } else {
+            a.key().cmp(b.key())
+        };
+        if options.reverse {
+            order.reverse()
+        } else {
+            order
+        }
+    };
+    let stats = if options.stats {
+        Some(Instrumented::new(&*sorter).sort_by(&mut records, &mut compare))
+    } else {
+        sorter.sort_by_dyn(&mut records, &mut compare);
+        None
+    };
+
+    if options.unique {
+        let unique = merge::dedup_sorted_by(&records, |a, b| compare(a, b) == Ordering::Equal);
+        for record in unique {
+            writeln!(out, "{}", record.line)?;
+        }
+    } else {
+        for record in &records {
+            writeln!(out, "{}", record.line)?;
+        }
+    }
+    Ok(stats)
+}
+
+struct TreeOptions {
+    print: bool,
+    dot: bool,
+    path: Option<String>,
+}
+
+impl TreeOptions {
+    fn parse(args: &[String]) -> Result<Self, Error> {
+        let mut options = TreeOptions {
+            print: false,
+            dot: false,
+            path: None,
+        };
+        for arg in args {
+            match arg.as_str() {
+                "--print" => options.print = true,
+                "--dot" => options.dot = true,
+                arg => positional(&mut options.path, arg)?,
+            }
+        }
+        if options.print && options.dot {
+            return Err(usage("--print and --dot can't be used together"));
+        }
+        Ok(options)
+    }
+}
+
+fn draw(tree: &RedBlackTree<i64, String>, out: &mut dyn Write) -> io::Result<()> {
+    if tree.is_empty() {
+        writeln!(out, "(empty)")
+    } else {
+        write!(out, "{}", tree.outline())
+    }
+}
+
+/// Runs the script in `input` against an empty tree, writing what it asks
+/// for to `out`.
+fn tree(options: &TreeOptions, input: impl BufRead, out: &mut dyn Write) -> Result<(), Error> {
+    let mut tree = RedBlackTree::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let bad = |message: &str| Error::Input(format!("line {}: {message}", i + 1));
+        let key = |word: Option<&str>| {
+            let word = word.ok_or_else(|| bad("missing key"))?;
+            word.parse::<i64>()
+                .map_err(|_| bad(&format!("`{word}` isn't an integer key")))
+        };
+
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim_start();
+        let mut words = rest.split_whitespace();
+        match command {
+            "insert" => {
+                let k = key(words.next())?;
+                let value = rest.split_once(char::is_whitespace).map_or("", |(_, v)| v);
+                tree.insert(k, value.trim().to_string());
+            }
+            "remove" => {
+                let k = key(words.next())?;
+                no_more(words, &bad)?;
+                if tree.remove(&k).is_none() {
+                    writeln!(out, "{k} not found")?;
+                }
+            }
+            "get" => {
+                let k = key(words.next())?;
+                no_more(words, &bad)?;
+                match tree.get(&k) {
+                    Some(value) => writeln!(out, "{value}")?,
+                    None => writeln!(out, "{k} not found")?,
+                }
+            }
+            "range" => {
+                let low = key(words.next())?;
+                let high = key(words.next())?;
+                no_more(words, &bad)?;
+                for (k, value) in tree.range(low..=high) {
+                    writeln!(out, "{k}\t{value}")?;
+                }
+            }
+            "len" => {
+                no_more(words, &bad)?;
+                writeln!(out, "{}", tree.len())?;
+            }
+            "print" => {
+                no_more(words, &bad)?;
+                draw(&tree, out)?;
+            }
+            "dot" => {
+                no_more(words, &bad)?;
+                write!(out, "{}", tree.dot())?;
+            }
+            command => return Err(bad(&format!("unknown command `{command}`"))),
+        }
+
+        if options.print {
+            writeln!(out, "> {line}")?;
+            draw(&tree, out)?;
+        } else if options.dot {
+            writeln!(out, "// {line}")?;
+            write!(out, "{}", tree.dot())?;
+        }
+    }
+    Ok(())
+}
+
+fn no_more<'a>(
+    mut words: impl Iterator<Item = &'a str>,
+    bad: &dyn Fn(&str) -> Error,
+) -> Result<(), Error> {
+    match words.next() {
+        Some(word) => Err(bad(&format!("unexpected `{word}`"))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(String::from).collect()
+    }
+
+    fn sorted(options: &str, input: &str) -> Result<String, Error> {
+        let options = SortOptions::parse(&args(options))?;
+        let mut out = Vec::new();
+        sort(&options, input.as_bytes(), &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    fn run_tree(options: &str, script: &str) -> Result<String, Error> {
+        let options = TreeOptions::parse(&args(options))?;
+        let mut out = Vec::new();
+        tree(&options, script.as_bytes(), &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn sorts_lines() {
+        let input = "pear 3\napple 10\nfig 2\napple 1\n";
+        let ok = |options, input| sorted(options, input).ok().unwrap();
+        assert_eq!(ok("", input), "apple 1\napple 10\nfig 2\npear 3\n");
+        assert_eq!(ok("-k 2 -n", input), "apple 1\nfig 2\npear 3\napple 10\n");
+        assert_eq!(ok("--key 2", input), "apple 1\napple 10\nfig 2\npear 3\n");
+        assert_eq!(
+            ok("-a merge -k 1 -u -r", input),
+            "pear 3\nfig 2\napple 10\n"
+        );
+        assert_eq!(ok("-n -a heap", "1e3\n-2\n 7\n"), "-2\n 7\n1e3\n");
+    }
+
+    #[test]
+    fn bad_sort_input() {
+        assert!(
+            matches!(sorted("-n", "1\ntwo\n"), Err(Error::Input(m)) if m.starts_with("line 2"))
+        );
+        assert!(matches!(sorted("-a bogo", ""), Err(Error::Usage(m)) if m.contains("quick")));
+        assert!(matches!(sorted("-k 0", ""), Err(Error::Usage(_))));
+        assert!(matches!(sorted("--frobnicate", ""), Err(Error::Usage(_))));
+        assert!(matches!(sorted("a b", ""), Err(Error::Usage(_))));
+    }
+
+    #[test]
+    fn runs_scripts() {
+        let script = "
+            # a comment
+            insert 5 five
+            insert 3 three
+            insert 8
+            get 3
+            get 4
+            range 4 8
+            remove 5
+            remove 5
+            len
+            print
+        ";
+        assert_eq!(
+            run_tree("", script).ok().unwrap(),
+            "three\n4 not found\n5\tfive\n8\t\n5 not found\n2\n8 (black)\n├── 3 (red)\n└── ·\n"
+        );
+
+        let steps = run_tree("--print", "insert 1\nremove 1\n").ok().unwrap();
+        assert_eq!(steps, "> insert 1\n1 (black)\n> remove 1\n(empty)\n");
+        let steps = run_tree("--dot", "insert 1\n").ok().unwrap();
+        assert!(steps.starts_with("// insert 1\ndigraph {"));
+    }
+
+    #[test]
+    fn bad_scripts() {
+        for script in ["insert x", "get", "frob 1", "range 1", "len 2"] {
+            assert!(
+                matches!(run_tree("", script), Err(Error::Input(_))),
+                "{script}"
+            );
+        }
+        assert!(matches!(
+            run_tree("--print --dot", ""),
+            Err(Error::Usage(_))
+        ));
+    }
+}
@@ -0,0 +1,517 @@
+//! A priority queue kept as a binary heap in a `Vec`.
+//!
+//! The heap is ordered by a comparator, the element that compares greatest
+//! being on top. It shares its sifts with
+//! [`HeapSort`](crate::sorting::HeapSort), and keeps track of where every
+//! element is through the same [`Observer`] that sorts report their swaps
+//! to, which is what lets [`Handle`]s find elements again.
+
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::cmp::Ordering;
+use core::mem;
+use core::ops::{Deref, DerefMut};
+
+use crate::sorting::heap_sort;
+use crate::sorting::instrument::{Observer, Probe};
+
+/// The position of a slot that holds no element.
+const VACANT: usize = usize::MAX;
+
+/// Refers to an element pushed onto a [`BinaryHeap`] for as long as it's in
+/// the heap, wherever sifting moves it.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Handle {
+    slot: usize,
+    generation: usize,
+}
+
+pub struct BinaryHeap<T, F = fn(&T, &T) -> Ordering> {
+    data: Vec<T>,
+    compare: F,
+    /// The slot of the element at each index of `data`.
+    slots: Vec<usize>,
+    /// The index in `data` of the element in each slot, or `VACANT`.
+    positions: Vec<usize>,
+    /// Bumped whenever a slot is freed, so old handles to it stop working.
+    generations: Vec<usize>,
+    free: Vec<usize>,
+}
+
+/// Follows the swaps a sift makes, keeping the slots and positions in step.
+struct Tracker<'a> {
+    slots: &'a [Cell<usize>],
+    positions: &'a [Cell<usize>],
+}
+
+impl<T> Observer<T> for Tracker<'_> {
+    fn swap(&self, i: usize, j: usize) {
+        self.slots[i].swap(&self.slots[j]);
+        self.positions[self.slots[i].get()].set(i);
+        self.positions[self.slots[j].get()].set(j);
+    }
+}
+
+impl<T: Ord> BinaryHeap<T> {
+    /// An empty heap with the greatest element on top.
+    pub fn new() -> Self {
+        BinaryHeap::with_comparator(T::cmp)
+    }
+
+    /// An empty heap with the smallest element on top.
+    pub fn new_min() -> Self {
+        BinaryHeap::with_comparator(|a: &T, b: &T| b.cmp(a))
+    }
+}
+
+impl<T: Ord> Default for BinaryHeap<T> {
+    fn default() -> Self {
+        BinaryHeap::new()
+    }
+}
+
+/// Builds a max-heap out of `vec` in linear time.
+impl<T: Ord> From<Vec<T>> for BinaryHeap<T> {
+    fn from(vec: Vec<T>) -> Self {
+        BinaryHeap::from_vec_by(vec, T::cmp)
+    }
+}
+
+impl<T: Ord> FromIterator<T> for BinaryHeap<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        BinaryHeap::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<T, F> BinaryHeap<T, F>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    /// An empty heap with the element that `compare` finds greatest on top.
+    ///
+    /// ```
+    /// use chonky_structures::data_structures::binary_heap::BinaryHeap;
+    ///
+    /// let mut heap = BinaryHeap::with_comparator(|a: &&str, b: &&str| a.len().cmp(&b.len()));
+    /// heap.push("bb");
+    /// heap.push("ccc");
+    /// heap.push("a");
+    /// assert_eq!(heap.pop(), Some("ccc"));
+    /// ```
+    pub fn with_comparator(compare: F) -> Self {
+        BinaryHeap {
+            data: Vec::new(),
+            compare,
+            slots: Vec::new(),
+            positions: Vec::new(),
+            generations: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// Builds a heap out of `vec` in linear time.
+    pub fn from_vec_by(vec: Vec<T>, compare: F) -> Self {
+        let len = vec.len();
+        let mut heap = BinaryHeap {
+            data: vec,
+            compare,
+            slots: (0..len).collect(),
+            positions: (0..len).collect(),
+            generations: alloc::vec![0; len],
+            free: Vec::new(),
+        };
+        heap.tracked(|data, compare, probe| heap_sort::build_heap(data, compare, probe));
+        heap
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// The elements in no particular order.
+    pub fn iter(&self) -> core::slice::Iter<'_, T> {
+        self.data.iter()
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.data.first()
+    }
+
+    /// The top element, which can be changed in place. The heap is fixed
+    /// up when the [`PeekMut`] is dropped, if it was changed.
+    ///
+    /// ```
+    /// use chonky_structures::data_structures::binary_heap::BinaryHeap;
+    ///
+    /// let mut heap = BinaryHeap::from(vec![1, 5, 3]);
+    /// *heap.peek_mut().unwrap() = 0;
+    /// assert_eq!(heap.peek(), Some(&3));
+    /// ```
+    pub fn peek_mut(&mut self) -> Option<PeekMut<'_, T, F>> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(PeekMut {
+                heap: self,
+                changed: false,
+            })
+        }
+    }
+
+    /// Adds `item` to the heap, and returns a handle to it.
+    pub fn push(&mut self, item: T) -> Handle {
+        let index = self.data.len();
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.positions[slot] = index;
+                slot
+            }
+            None => {
+                self.positions.push(index);
+                self.generations.push(0);
+                self.positions.len() - 1
+            }
+        };
+        self.data.push(item);
+        self.slots.push(slot);
+        self.tracked(|data, compare, probe| heap_sort::sift_up(data, index, compare, probe));
+        Handle {
+            slot,
+            generation: self.generations[slot],
+        }
+    }
+
+    /// Removes the top element.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.remove_at(0))
+        }
+    }
+
+    /// The element `handle` refers to, or `None` if it's left the heap.
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.index_of(handle).map(|i| &self.data[i])
+    }
+
+    /// Changes the element `handle` refers to with `f`, and moves it up or
+    /// down to where it now belongs. This is the decrease-key operation
+    /// Dijkstra's and Prim's algorithms need from a min-heap. Returns false
+    /// if the element has left the heap.
+    ///
+    /// ```
+    /// use chonky_structures::data_structures::binary_heap::BinaryHeap;
+    ///
+    /// let mut heap = BinaryHeap::new_min();
+    /// heap.push(10);
+    /// let far = heap.push(30);
+    /// assert!(heap.update(far, |d| *d = 5));
+    /// assert_eq!(heap.pop(), Some(5));
+    /// assert!(!heap.update(far, |d| *d = 1));
+    /// ```
+    pub fn update(&mut self, handle: Handle, f: impl FnOnce(&mut T)) -> bool {
+        match self.index_of(handle) {
+            Some(index) => {
+                f(&mut self.data[index]);
+                self.restore(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes the element `handle` refers to, if it's still in the heap.
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        self.index_of(handle).map(|i| self.remove_at(i))
+    }
+
+    pub fn clear(&mut self) {
+        for slot in mem::take(&mut self.slots) {
+            self.free_slot(slot);
+        }
+        self.data.clear();
+    }
+
+    /// Removes the elements in order, top first. Whatever isn't taken is
+    /// removed when the iterator is dropped.
+    ///
+    /// ```
+    /// use chonky_structures::data_structures::binary_heap::BinaryHeap;
+    ///
+    /// let mut heap = BinaryHeap::from(vec![2, 7, 4]);
+    /// assert!(heap.drain_sorted().eq([7, 4, 2]));
+    /// assert!(heap.is_empty());
+    /// ```
+    pub fn drain_sorted(&mut self) -> DrainSorted<'_, T, F> {
+        DrainSorted { heap: self }
+    }
+
+    /// The elements in no particular order.
+    pub fn into_vec(self) -> Vec<T> {
+        self.data
+    }
+
+    /// The elements sorted so that the top of the heap comes last, by the
+    /// second half of a heap sort.
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        heap_sort::sort_heap(&mut self.data, &mut self.compare);
+        self.data
+    }
+
+    fn index_of(&self, handle: Handle) -> Option<usize> {
+        let index = *self.positions.get(handle.slot)?;
+        (index != VACANT && self.generations[handle.slot] == handle.generation).then_some(index)
+    }
+
+    fn free_slot(&mut self, slot: usize) {
+        self.positions[slot] = VACANT;
+        self.generations[slot] += 1;
+        self.free.push(slot);
+    }
+
+    /// Removes the element at `index` by swapping the last element into its
+    /// place and sifting that.
+    fn remove_at(&mut self, index: usize) -> T {
+        let last = self.data.len() - 1;
+        self.tracked(|data, _, probe| probe.swap(data, index, last));
+        let slot = self.slots.pop().unwrap();
+        self.free_slot(slot);
+        let item = self.data.pop().unwrap();
+        if index < self.data.len() {
+            self.restore(index);
+        }
+        item
+    }
+
+    /// Moves the element at `index` up or down to where it belongs.
+    fn restore(&mut self, index: usize) {
+        self.tracked(|data, compare, probe| {
+            if heap_sort::sift_up(data, index, compare, probe) == index {
+                heap_sort::sift_down(data, index, compare, probe);
+            }
+        });
+    }
+
+    /// Runs `f` with a probe that reports swaps to a [`Tracker`].
+    fn tracked<R>(&mut self, f: impl FnOnce(&mut [T], &mut F, &Probe<T, Tracker>) -> R) -> R {
+        let tracker = Tracker {
+            slots: Cell::from_mut(&mut self.slots[..]).as_slice_of_cells(),
+            positions: Cell::from_mut(&mut self.positions[..]).as_slice_of_cells(),
+        };
+        let probe = Probe::by_position(&tracker);
+        f(&mut self.data, &mut self.compare, &probe)
+    }
+}
+
+/// The top element of a [`BinaryHeap`], created by
+/// [`BinaryHeap::peek_mut`].
+pub struct PeekMut<'a, T, F>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    heap: &'a mut BinaryHeap<T, F>,
+    changed: bool,
+}
+
+impl<T, F> PeekMut<'_, T, F>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    /// Removes the peeked element from the heap.
+    pub fn pop(mut this: Self) -> T {
+        this.changed = false;
+        this.heap.remove_at(0)
+    }
+}
+
+impl<T, F> Deref for PeekMut<'_, T, F>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.heap.data[0]
+    }
+}
+
+impl<T, F> DerefMut for PeekMut<'_, T, F>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        self.changed = true;
+        &mut self.heap.data[0]
+    }
+}
+
+impl<T, F> Drop for PeekMut<'_, T, F>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    fn drop(&mut self) {
+        if self.changed {
+            self.heap
+                .tracked(|data, compare, probe| heap_sort::sift_down(data, 0, compare, probe));
+        }
+    }
+}
+
+/// Pops the elements of a [`BinaryHeap`] in order, created by
+/// [`BinaryHeap::drain_sorted`].
+pub struct DrainSorted<'a, T, F>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    heap: &'a mut BinaryHeap<T, F>,
+}
+
+impl<T, F> Iterator for DrainSorted<'_, T, F>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.heap.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.heap.len(), Some(self.heap.len()))
+    }
+}
+
+impl<T, F> ExactSizeIterator for DrainSorted<'_, T, F> where F: FnMut(&T, &T) -> Ordering {}
+
+impl<T, F> Drop for DrainSorted<'_, T, F>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    fn drop(&mut self) {
+        self.heap.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorting::inputs;
+
+    /// Checks the heap property, and that every slot and position agree.
+    fn check<T, F: FnMut(&T, &T) -> Ordering>(heap: &mut BinaryHeap<T, F>) {
+        for i in 1..heap.len() {
+            let parent = (i - 1) / 2;
+            assert!((heap.compare)(&heap.data[parent], &heap.data[i]) != Ordering::Less);
+        }
+        check_slots(heap);
+    }
+
+    fn check_slots<T, F>(heap: &BinaryHeap<T, F>) {
+        assert_eq!(heap.slots.len(), heap.data.len());
+        for (i, &slot) in heap.slots.iter().enumerate() {
+            assert_eq!(heap.positions[slot], i);
+        }
+    }
+
+    #[test]
+    fn push_and_pop() {
+        let list = inputs::random(500, 1);
+        let mut sorted = list.clone();
+        sorted.sort();
+
+        let mut heap = BinaryHeap::new();
+        for &x in &list {
+            heap.push(x);
+        }
+        check(&mut heap);
+        assert_eq!(heap.peek(), sorted.last());
+        let popped: Vec<_> = core::iter::from_fn(|| heap.pop()).collect();
+        assert!(popped.iter().eq(sorted.iter().rev()));
+
+        let mut heap = BinaryHeap::new_min();
+        for &x in &list {
+            heap.push(x);
+        }
+        assert!(heap.drain_sorted().eq(sorted.iter().copied()));
+
+        let mut heap = BinaryHeap::from(list.clone());
+        check(&mut heap);
+        assert_eq!(heap.into_sorted_vec(), sorted);
+        let heap: BinaryHeap<_> = list.iter().copied().collect();
+        assert_eq!(heap.len(), 500);
+    }
+
+    #[test]
+    fn peek_mut() {
+        let mut heap = BinaryHeap::from(inputs::random(100, 2));
+        let top = *heap.peek().unwrap();
+        *heap.peek_mut().unwrap() = 0;
+        check(&mut heap);
+        assert!(*heap.peek().unwrap() < top);
+        let next = *heap.peek().unwrap();
+        assert_eq!(PeekMut::pop(heap.peek_mut().unwrap()), next);
+        check(&mut heap);
+        assert_eq!(heap.len(), 99);
+    }
+
+    #[test]
+    fn handles() {
+        let list = inputs::random(300, 3);
+        let mut heap = BinaryHeap::new_min();
+        let handles: Vec<_> = list.iter().map(|&x| heap.push(x)).collect();
+        for (i, &handle) in handles.iter().enumerate().step_by(3) {
+            assert_eq!(heap.get(handle), Some(&list[i]));
+            heap.update(handle, |x| *x /= 2);
+            check(&mut heap);
+        }
+        for &handle in handles.iter().skip(1).step_by(3) {
+            heap.update(handle, |x| *x = x.wrapping_mul(3));
+            check(&mut heap);
+        }
+        let removed = heap.remove(handles[2]).unwrap();
+        check(&mut heap);
+        assert_eq!(heap.remove(handles[2]), None);
+        assert_eq!(heap.len(), 299);
+
+        let mut expected: Vec<_> = heap.iter().copied().collect();
+        expected.sort();
+        let top = heap.pop().unwrap();
+        assert_eq!(top, expected[0]);
+        assert!(handles.iter().filter(|&&h| heap.get(h).is_none()).count() == 2);
+
+        // freed slots are reused without reviving the old handles
+        let new = heap.push(removed);
+        assert_ne!(new, handles[2]);
+        assert_eq!(heap.get(new), Some(&removed));
+        heap.clear();
+        assert!(handles.iter().all(|&h| heap.get(h).is_none()));
+    }
+
+    #[test]
+    fn zero_sized() {
+        let mut heap = BinaryHeap::new();
+        let (a, b, c) = (heap.push(()), heap.push(()), heap.push(()));
+        assert_eq!(heap.remove(a), Some(()));
+        assert_eq!(heap.get(a), None);
+        assert!(heap.get(b).is_some() && heap.get(c).is_some());
+        check(&mut heap);
+
+        // every comparison asks for a swap, so each push sifts to the top
+        let mut heap = BinaryHeap::with_comparator(|_: &(), _: &()| Ordering::Less);
+        let handles: Vec<_> = (0..10).map(|_| heap.push(())).collect();
+        assert_eq!(heap.slots[0], handles[9].slot);
+        check_slots(&heap);
+        assert_eq!(heap.remove(handles[3]), Some(()));
+        check_slots(&heap);
+        assert_eq!(heap.get(handles[3]), None);
+        assert!(handles
+            .iter()
+            .enumerate()
+            .all(|(i, &h)| heap.get(h).is_some() == (i != 3)));
+    }
+}
@@ -1 +1,3 @@
+pub mod allocator;
+pub mod binary_heap;
 pub mod red_black_tree;
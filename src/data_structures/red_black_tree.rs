@@ -1,7 +1,15 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::alloc::Layout;
 use core::borrow::Borrow;
 use core::cmp::Ordering;
+use core::fmt::{Display, Write};
 use core::marker::PhantomData;
-use core::ptr;
+use core::ops::{Bound, RangeBounds};
+use core::ptr::{self, NonNull};
+
+use super::allocator::{Allocator, Global};
+use crate::sorting::{Sorter, TimSort};
 
 struct NodePtr<K, V>(*mut Node<K, V>);
 
@@ -14,16 +22,17 @@ impl<K, V> NodePtr<K, V> {
         self.0.is_null()
     }
 
+    #[allow(clippy::mut_from_ref)]
     fn get_node(&self) -> &mut Node<K, V> {
         assert!(!self.is_null());
         unsafe { &mut (*self.0) }
     }
 
-    fn key<'a>(&'a self) -> &'a K {
+    fn key(&self) -> &K {
         &self.get_node().val.0
     }
 
-    fn val_mut<'a>(&'a mut self) -> &'a mut V {
+    fn val_mut(&mut self) -> &mut V {
         &mut self.get_node().val.1
     }
 
@@ -100,8 +109,12 @@ impl<K, V> NodePtr<K, V> {
         }
     }
 
+    fn colour(&self) -> Colour {
+        self.get_node().colour
+    }
+
     fn set_colour(&mut self, c: Colour) {
-        (*self.get_node()).colour = c;
+        self.get_node().colour = c;
     }
 
     fn is_black(&self) -> bool {
@@ -119,7 +132,7 @@ impl<K, V> NodePtr<K, V> {
 
 impl<K, V> Clone for NodePtr<K, V> {
     fn clone(&self) -> Self {
-        NodePtr(self.0)
+        *self
     }
 }
 
@@ -158,19 +171,32 @@ impl<K, V> Node<K, V> {
     }
 }
 
-pub struct RedBlackTree<K, V> {
+pub struct RedBlackTree<K, V, A: Allocator = Global> {
     root: NodePtr<K, V>,
     len: usize,
+    alloc: A,
 }
 
 impl<K, V> RedBlackTree<K, V> {
     pub fn new() -> Self {
+        Self::new_in(Global)
+    }
+}
+
+impl<K, V, A: Allocator> RedBlackTree<K, V, A> {
+    /// Creates an empty tree whose nodes are allocated with `alloc`.
+    pub fn new_in(alloc: A) -> Self {
         Self {
             root: NodePtr::null(),
             len: 0,
+            alloc,
         }
     }
 
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
     pub fn len(&self) -> usize {
         self.len
     }
@@ -179,21 +205,49 @@ impl<K, V> RedBlackTree<K, V> {
         self.len == 0
     }
 
+    /// Moves a new node holding `k` and `v` into memory obtained from the
+    /// tree's allocator.
+    fn alloc_node(&self, k: K, v: V) -> NodePtr<K, V> {
+        let layout = Layout::new::<Node<K, V>>();
+        let ptr = match self.alloc.allocate(layout) {
+            Ok(ptr) => ptr.cast::<Node<K, V>>(),
+            Err(_) => alloc::alloc::handle_alloc_error(layout),
+        };
+        unsafe { ptr.as_ptr().write(Node::new(k, v)) };
+        NodePtr(ptr.as_ptr())
+    }
+
+    /// Moves the key and value out of `node` and gives its memory back to
+    /// the tree's allocator.
+    ///
+    /// # Safety
+    ///
+    /// `node` must have come from `alloc_node` on this tree and must not be
+    /// reachable from the tree anymore.
+    unsafe fn free_node(&self, node: NodePtr<K, V>) -> (K, V) {
+        let Node { val, .. } = ptr::read(node.0);
+        self.alloc.deallocate(
+            NonNull::new_unchecked(node.0).cast(),
+            Layout::new::<Node<K, V>>(),
+        );
+        val
+    }
+
     #[inline]
-    fn clear_node(node: NodePtr<K, V>) {
+    fn clear_node(&self, node: NodePtr<K, V>) {
         if node.is_null() {
             return;
         }
 
-        unsafe {
-            Self::clear_node(node.right());
-            Self::clear_node(node.left());
-            Box::from_raw(node.0);
-        }
+        self.clear_node(node.right());
+        self.clear_node(node.left());
+        unsafe { self.free_node(node) };
     }
 
     fn clear(&mut self) {
-        Self::clear_node(self.root);
+        self.clear_node(self.root);
+        self.root = NodePtr::null();
+        self.len = 0;
     }
 }
 
@@ -203,9 +257,10 @@ impl<K, V> Default for RedBlackTree<K, V> {
     }
 }
 
-impl<K, V> RedBlackTree<K, V>
+impl<K, V, A> RedBlackTree<K, V, A>
 where
     K: Ord,
+    A: Allocator,
 {
     /// Rotates the subtree starting at `node` in the given direction,
     /// true representing a right rotation and false a left rotation
@@ -292,22 +347,19 @@ where
         }
 
         if self.root.is_null() {
-            let node = Box::new(Node::new(k, v));
-            let node = NodePtr(Box::leak(node));
+            let node = self.alloc_node(k, v);
             self.root = node;
             Some(self.root)
         } else {
             match k.cmp(cur_node.key()) {
                 Ordering::Less => {
-                    let node = Box::new(Node::new(k, v));
-                    let mut node = NodePtr(Box::leak(node));
+                    let mut node = self.alloc_node(k, v);
                     cur_node.set_left(&node);
                     node.set_parent(&cur_node);
                     Some(node)
                 }
                 Ordering::Greater => {
-                    let node = Box::new(Node::new(k, v));
-                    let mut node = NodePtr(Box::leak(node));
+                    let mut node = self.alloc_node(k, v);
                     cur_node.set_right(&node);
                     node.set_parent(&cur_node);
                     Some(node)
@@ -336,17 +388,109 @@ where
         Q: Ord + ?Sized,
     {
         let node = self.find_node(k);
-        self.delete(node);
+        if node.is_null() {
+            return None;
+        }
+        let (_, v) = self.delete(node);
         self.len -= 1;
-        None
+        Some(v)
     }
 
-    fn delete<Q>(&mut self, node: NodePtr<K, V>)
-    where
-        K: Borrow<Q>,
-        Q: Ord + ?Sized,
-    {
-        unimplemented!()
+    /// Replaces the subtree rooted at `old` with the one rooted at `new`
+    /// in `old`'s parent.
+    fn transplant(&mut self, old: NodePtr<K, V>, mut new: NodePtr<K, V>) {
+        if old.parent().is_null() {
+            self.root = new;
+        } else {
+            old.parent().set_child(&new, old.is_right_child());
+        }
+        if !new.is_null() {
+            new.set_parent(&old.parent());
+        }
+    }
+
+    /// Returns the node with the smallest key in the subtree rooted at `node`
+    fn minimum(mut node: NodePtr<K, V>) -> NodePtr<K, V> {
+        while !node.left().is_null() {
+            node = node.left();
+        }
+        node
+    }
+
+    /// Unlinks `node` from the tree, rebalances it, and hands back the
+    /// key and value it held.
+    fn delete(&mut self, node: NodePtr<K, V>) -> (K, V) {
+        let mut removed_colour = node.colour();
+        let child;
+        let child_parent;
+        if node.left().is_null() {
+            child = node.right();
+            child_parent = node.parent();
+            self.transplant(node, child);
+        } else if node.right().is_null() {
+            child = node.left();
+            child_parent = node.parent();
+            self.transplant(node, child);
+        } else {
+            let mut successor = Self::minimum(node.right());
+            removed_colour = successor.colour();
+            child = successor.right();
+            if successor.parent() == node {
+                child_parent = successor;
+            } else {
+                child_parent = successor.parent();
+                self.transplant(successor, successor.right());
+                successor.set_right(&node.right());
+                successor.right().set_parent(&successor);
+            }
+            self.transplant(node, successor);
+            successor.set_left(&node.left());
+            successor.left().set_parent(&successor);
+            successor.set_colour(node.colour());
+        }
+
+        if removed_colour == Colour::Black {
+            self.correct_after_delete(child, child_parent);
+        }
+
+        unsafe { self.free_node(node) }
+    }
+
+    /// Restores the Red Black Tree criteria after a black node was removed
+    /// from above `node`. `node` may be null, so its parent is passed in
+    /// separately.
+    fn correct_after_delete(&mut self, mut node: NodePtr<K, V>, mut parent: NodePtr<K, V>) {
+        while node != self.root && node.is_black() {
+            let is_right = parent.right() == node;
+            let mut sibling = parent.child_dir(!is_right);
+            if sibling.is_red() {
+                sibling.set_colour(Colour::Black);
+                parent.set_colour(Colour::Red);
+                self.rotate_dir(parent, is_right);
+                sibling = parent.child_dir(!is_right);
+            }
+
+            if sibling.left().is_black() && sibling.right().is_black() {
+                sibling.set_colour(Colour::Red);
+                node = parent;
+                parent = node.parent();
+            } else {
+                if sibling.child_dir(!is_right).is_black() {
+                    sibling.child_dir(is_right).set_colour(Colour::Black);
+                    sibling.set_colour(Colour::Red);
+                    self.rotate_dir(sibling, !is_right);
+                    sibling = parent.child_dir(!is_right);
+                }
+                sibling.set_colour(parent.colour());
+                parent.set_colour(Colour::Black);
+                sibling.child_dir(!is_right).set_colour(Colour::Black);
+                self.rotate_dir(parent, is_right);
+                node = self.root;
+            }
+        }
+        if !node.is_null() {
+            node.set_colour(Colour::Black);
+        }
     }
 
     fn find_node<Q>(&self, k: &Q) -> NodePtr<K, V>
@@ -398,14 +542,68 @@ where
     {
         self.get(k).is_some()
     }
+
+    /// The first node whose key is past `bound`, or null if there isn't
+    /// one.
+    fn lower_bound<Q>(&self, bound: Bound<&Q>) -> NodePtr<K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut next_node = self.root;
+        let mut found = NodePtr::null();
+        while !next_node.is_null() {
+            let key = next_node.key().borrow();
+            let past = match bound {
+                Bound::Included(b) => key >= b,
+                Bound::Excluded(b) => key > b,
+                Bound::Unbounded => true,
+            };
+            if past {
+                found = next_node;
+                next_node = next_node.left();
+            } else {
+                next_node = next_node.right();
+            }
+        }
+        found
+    }
+
+    /// Iterates over the entries with keys in `range`, in order. A range
+    /// that ends before it starts is empty.
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let mut start = self.lower_bound(range.start_bound());
+        let end = match range.end_bound() {
+            Bound::Included(b) => self.lower_bound(Bound::Excluded(b)),
+            Bound::Excluded(b) => self.lower_bound(Bound::Included(b)),
+            Bound::Unbounded => NodePtr::null(),
+        };
+        if !start.is_null() && !end.is_null() && end.key() < start.key() {
+            start = end;
+        }
+        Range {
+            iter: Iter {
+                head: start,
+                tail: NodePtr::null(),
+                remaining: self.len,
+                _marker: PhantomData,
+            },
+            end,
+        }
+    }
 }
 
-impl<K, V> RedBlackTree<K, V> {
-    pub fn iter(&self) -> Iter<K, V> {
+impl<K, V, A: Allocator> RedBlackTree<K, V, A> {
+    pub fn iter(&self) -> Iter<'_, K, V> {
         Iter::new(self.root, self.root, self.len)
     }
 
-    pub fn values(&self) -> Values<K, V> {
+    pub fn values(&self) -> Values<'_, K, V> {
         let iter = self.iter();
         Values {
             iter,
@@ -413,28 +611,155 @@ impl<K, V> RedBlackTree<K, V> {
         }
     }
 
-    pub fn keys(&self) -> Keys<K, V> {
+    pub fn keys(&self) -> Keys<'_, K, V> {
         let iter = self.iter();
         Keys {
             iter,
             _marker: PhantomData,
         }
     }
+
+    /// The entries in order of value, entries with equal values staying in
+    /// order of key.
+    ///
+    /// ```
+    /// use chonky_structures::data_structures::red_black_tree::RedBlackTree;
+    ///
+    /// let mut tree = RedBlackTree::new();
+    /// tree.insert("b", 1);
+    /// tree.insert("a", 2);
+    /// tree.insert("c", 1);
+    /// let by_value: Vec<_> = tree.sorted_by_value().iter().map(|(k, _)| *k).collect();
+    /// assert_eq!(by_value, ["b", "c", "a"]);
+    /// ```
+    pub fn sorted_by_value(&self) -> ByValue<'_, K, V>
+    where
+        V: Ord,
+    {
+        self.sorted_by_value_by(V::cmp)
+    }
+
+    pub fn sorted_by_value_by<F>(&self, mut compare: F) -> ByValue<'_, K, V>
+    where
+        F: FnMut(&V, &V) -> Ordering,
+    {
+        let mut entries: Vec<_> = self.iter().collect();
+        TimSort.sort_by(&mut entries, |a, b| compare(a.1, b.1));
+        ByValue { entries }
+    }
+}
+
+impl<K: Display, V, A: Allocator> RedBlackTree<K, V, A> {
+    /// Draws the tree as text, a node per line with its colour and its left
+    /// child listed before its right. A missing child is drawn as `·` when
+    /// its sibling isn't missing.
+    ///
+    /// ```
+    /// use chonky_structures::data_structures::red_black_tree::RedBlackTree;
+    ///
+    /// let mut tree = RedBlackTree::new();
+    /// for k in [2, 1, 3, 4] {
+    ///     tree.insert(k, ());
+    /// }
+    /// assert_eq!(
+    ///     tree.outline(),
+    ///     "2 (black)\n├── 1 (black)\n└── 3 (black)\n    ├── ·\n    └── 4 (red)\n"
+    /// );
+    /// ```
+    pub fn outline(&self) -> String {
+        let mut out = String::new();
+        if !self.root.is_null() {
+            outline_node(self.root, "", "", &mut out);
+        }
+        out
+    }
+
+    /// Draws the tree in Graphviz's DOT language, with nodes filled in their
+    /// colour.
+    pub fn dot(&self) -> String {
+        let mut out = String::from("digraph {\n    node [style=filled, fontcolor=white];\n");
+        if !self.root.is_null() {
+            dot_node(self.root, &mut 0, &mut out);
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+fn colour_name<K, V>(node: NodePtr<K, V>) -> &'static str {
+    match node.colour() {
+        Colour::Red => "red",
+        Colour::Black => "black",
+    }
 }
 
-impl<K, V> Drop for RedBlackTree<K, V> {
+/// Draws `node` after `first`, and its children after `rest`.
+fn outline_node<K: Display, V>(node: NodePtr<K, V>, first: &str, rest: &str, out: &mut String) {
+    let _ = writeln!(out, "{first}{} ({})", node.key(), colour_name(node));
+    if node.left().is_null() && node.right().is_null() {
+        return;
+    }
+    for (child, last) in [(node.left(), false), (node.right(), true)] {
+        let (branch, indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        if child.is_null() {
+            let _ = writeln!(out, "{rest}{branch}·");
+        } else {
+            let first = alloc::format!("{rest}{branch}");
+            let rest = alloc::format!("{rest}{indent}");
+            outline_node(child, &first, &rest, out);
+        }
+    }
+}
+
+/// Writes `node` and its subtree, numbering nodes from `next`, and returns
+/// the name of `node`.
+fn dot_node<K: Display, V>(node: NodePtr<K, V>, next: &mut usize, out: &mut String) -> String {
+    let name = alloc::format!("n{next}");
+    *next += 1;
+    let label = alloc::format!("{}", node.key())
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+    let _ = writeln!(
+        out,
+        "    {name} [label=\"{label}\", fillcolor={}];",
+        colour_name(node)
+    );
+    if node.left().is_null() && node.right().is_null() {
+        return name;
+    }
+    for child in [node.left(), node.right()] {
+        let child_name = if child.is_null() {
+            // keep left and right children on their own sides
+            let nil = alloc::format!("nil{next}");
+            *next += 1;
+            let _ = writeln!(out, "    {nil} [shape=point, fillcolor=black];");
+            nil
+        } else {
+            dot_node(child, next, out)
+        };
+        let _ = writeln!(out, "    {name} -> {child_name};");
+    }
+    name
+}
+
+impl<K, V, A: Allocator> Drop for RedBlackTree<K, V, A> {
     fn drop(&mut self) {
         self.clear();
     }
 }
 
-impl<K, V> Clone for RedBlackTree<K, V>
+impl<K, V, A> Clone for RedBlackTree<K, V, A>
 where
     K: Ord + Clone,
     V: Clone,
+    A: Allocator + Clone,
 {
     fn clone(&self) -> Self {
-        let mut copy = Self::new();
+        let mut copy = Self::new_in(self.alloc.clone());
         let iter = self.iter();
         for (k, v) in iter {
             copy.insert(k.clone(), v.clone());
@@ -561,6 +886,63 @@ impl<'a, K: 'a, V: 'a> DoubleEndedIterator for Iter<'a, K, V> {
         }
     }
 }
+/// The entries of a [`RedBlackTree`] with keys in a range, created by
+/// [`RedBlackTree::range`].
+#[derive(Copy, Clone)]
+pub struct Range<'a, K: 'a, V: 'a> {
+    iter: Iter<'a, K, V>,
+    /// The first node after the range.
+    end: NodePtr<K, V>,
+}
+
+impl<'a, K: 'a, V: 'a> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.iter.head == self.end {
+            None
+        } else {
+            self.iter.next()
+        }
+    }
+}
+
+/// The entries of a [`RedBlackTree`] sorted by value, created by
+/// [`RedBlackTree::sorted_by_value`]. It borrows the tree, so it can't go
+/// stale.
+#[derive(Clone)]
+pub struct ByValue<'a, K: 'a, V: 'a> {
+    entries: Vec<(&'a K, &'a V)>,
+}
+
+impl<'a, K: 'a, V: 'a> ByValue<'a, K, V> {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entry with the `index`th smallest value.
+    pub fn get(&self, index: usize) -> Option<(&'a K, &'a V)> {
+        self.entries.get(index).copied()
+    }
+
+    pub fn iter(&self) -> core::iter::Copied<core::slice::Iter<'_, (&'a K, &'a V)>> {
+        self.entries.iter().copied()
+    }
+}
+
+impl<'a, K: 'a, V: 'a> IntoIterator for ByValue<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = alloc::vec::IntoIter<(&'a K, &'a V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct Keys<'a, K: 'a, V: 'a> {
     iter: Iter<'a, K, V>,
@@ -601,8 +983,16 @@ impl<'a, K: 'a, V: 'a> DoubleEndedIterator for Values<'a, K, V> {
     }
 }
 
+#[cfg(test)]
 mod tests {
-    use super::{Iter, RedBlackTree};
+    use super::{NodePtr, RedBlackTree};
+    use crate::data_structures::allocator::{AllocError, Allocator, Global};
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use core::alloc::Layout;
+    use core::cell::Cell;
+    use core::ptr::NonNull;
+
     #[test]
     fn empty() {
         let _x: RedBlackTree<u32, u32> = RedBlackTree::new();
@@ -628,10 +1018,161 @@ mod tests {
         let vec: Vec<_> = iter.collect();
         assert_eq!(vec, [&2, &3, &4, &5, &6, &7, &9, &12, &15]);
     }
+
+    /// Returns the black height of the subtree at `node`, panicking if any
+    /// of the Red Black Tree criteria don't hold.
+    fn black_height<K: Ord, V>(node: NodePtr<K, V>) -> usize {
+        if node.is_null() {
+            return 1;
+        }
+        if node.is_red() {
+            assert!(node.left().is_black() && node.right().is_black());
+        }
+        if !node.left().is_null() {
+            assert!(node.left().key() < node.key());
+            assert!(node.left().parent() == node);
+        }
+        if !node.right().is_null() {
+            assert!(node.right().key() > node.key());
+            assert!(node.right().parent() == node);
+        }
+        let height = black_height(node.left());
+        assert_eq!(height, black_height(node.right()));
+        height + usize::from(node.is_black())
+    }
+
+    #[test]
+    fn remove() {
+        let mut tree = RedBlackTree::new();
+        for i in 0..64 {
+            tree.insert((i * 37) % 64, i);
+        }
+        black_height(tree.root);
+
+        for i in (0..64).step_by(3) {
+            assert_eq!(tree.remove(&((i * 37) % 64)), Some(i));
+            assert_eq!(tree.remove(&((i * 37) % 64)), None);
+            black_height(tree.root);
+        }
+        assert_eq!(tree.len(), 64 - 22);
+
+        let keys: Vec<_> = tree.keys().copied().collect();
+        let expected: Vec<_> = (0..64)
+            .filter(|k| (0..64).step_by(3).all(|i| (i * 37) % 64 != *k))
+            .collect();
+        assert_eq!(keys, expected);
+    }
+
+    #[derive(Default)]
+    struct CountingAllocator {
+        allocs: Cell<usize>,
+        frees: Cell<usize>,
+    }
+
+    impl CountingAllocator {
+        fn live(&self) -> usize {
+            self.allocs.get() - self.frees.get()
+        }
+    }
+
+    unsafe impl Allocator for CountingAllocator {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+            self.allocs.set(self.allocs.get() + 1);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            self.frees.set(self.frees.get() + 1);
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn allocator_does_not_leak() {
+        let alloc = CountingAllocator::default();
+        {
+            let mut tree = RedBlackTree::new_in(&alloc);
+            for i in 0..100 {
+                tree.insert(i, i.to_string());
+            }
+            // replacing a value must not allocate a new node
+            tree.insert(50, String::from("fifty"));
+            assert_eq!(alloc.live(), 100);
+
+            for i in (0..100).step_by(2) {
+                assert!(tree.remove(&i).is_some());
+            }
+            assert_eq!(alloc.live(), 50);
+
+            let copy = tree.clone();
+            assert_eq!(alloc.live(), 100);
+            drop(copy);
+            assert_eq!(alloc.live(), 50);
+        }
+        assert_eq!(alloc.live(), 0);
+        assert_eq!(alloc.allocs.get(), 150);
+    }
+
+    #[test]
+    fn range() {
+        let mut tree = RedBlackTree::new();
+        for i in (0..100).rev() {
+            tree.insert(i * 2, i);
+        }
+        let keys = |range: (core::ops::Bound<u32>, core::ops::Bound<u32>)| {
+            tree.range(range).map(|(k, _)| *k).collect::<Vec<_>>()
+        };
+        use core::ops::Bound::*;
+        assert_eq!(keys((Included(10), Excluded(16))), [10, 12, 14]);
+        assert_eq!(keys((Excluded(9), Included(16))), [10, 12, 14, 16]);
+        assert_eq!(keys((Included(195), Unbounded)), [196, 198]);
+        assert_eq!(keys((Unbounded, Excluded(3))), [0, 2]);
+        assert_eq!(keys((Included(20), Included(10))), []);
+        assert_eq!(keys((Included(500), Unbounded)), []);
+        assert_eq!(tree.range(..).count(), 100);
+        assert_eq!(tree.range(7..=7).count(), 0);
+        assert_eq!(tree.range(8..=8).next(), Some((&8, &4)));
+    }
+
+    #[test]
+    fn dot() {
+        let mut tree = RedBlackTree::new();
+        for k in ["b", "a", "c", "d\""] {
+            tree.insert(k, ());
+        }
+        let dot = tree.dot();
+        assert!(dot.starts_with("digraph {"));
+        assert_eq!(dot.matches("fillcolor=red").count(), 1);
+        assert!(dot.contains(r#"label="d\"""#));
+        assert!(dot.contains("[shape=point"));
+        assert_eq!(RedBlackTree::<u8, u8>::new().outline(), "");
+    }
+
+    #[test]
+    fn sorted_by_value() {
+        let mut tree = RedBlackTree::new();
+        for k in 0..100 {
+            tree.insert(k, k % 7);
+        }
+        let by_value = tree.sorted_by_value();
+        assert_eq!(by_value.len(), 100);
+        assert_eq!(by_value.get(0), Some((&0, &0)));
+        assert_eq!(by_value.get(14), Some((&98, &0)));
+        assert!(by_value
+            .iter()
+            .zip(by_value.iter().skip(1))
+            .all(|((k1, v1), (k2, v2))| (v1, k1) < (v2, k2)));
+
+        let reversed: Vec<_> = tree
+            .sorted_by_value_by(|a, b| b.cmp(a))
+            .into_iter()
+            .collect();
+        assert_eq!(reversed[0], (&6, &6));
+        assert!(RedBlackTree::<u8, u8>::new().sorted_by_value().is_empty());
+    }
 }
 
 #[test]
-#[should_panic]
 fn test_lots_of_insertions() {
     let mut m = RedBlackTree::new();
 
@@ -1,21 +1,236 @@
+use core::cmp::Ordering;
+
+use instrument::{Observer, Probe};
+
+/// How the number of operations a sorter performs grows with the length of
+/// the slice being sorted.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Complexity {
+    /// O(n)
+    Linear,
+    /// O(n log n)
+    Linearithmic,
+    /// O(n log² n)
+    LinearithmicSquared,
+    /// O(n^(4/3))
+    FourThirds,
+    /// O(n^(3/2))
+    ThreeHalves,
+    /// O(n^2)
+    Quadratic,
+}
+
+/// Describes the behaviour of a [`Sorter`], so an algorithm can be picked by
+/// what it guarantees rather than by name.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Properties {
+    /// Equal elements keep their relative order.
+    pub stable: bool,
+    /// The sorter doesn't allocate a buffer proportional to the input.
+    pub in_place: bool,
+    /// The sorter only works on elements that are `Clone`.
+    pub requires_clone: bool,
+    pub best: Complexity,
+    pub average: Complexity,
+    pub worst: Complexity,
+}
+
 pub trait Sorter<T> {
+    /// Sorts `slice` according to `compare`.
+    ///
+    /// This is the object safe entry point every sorter implements, the
+    /// other sorting methods all delegate to it.
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering);
+
+    fn properties(&self) -> Properties;
+
+    /// Sorts `slice` according to `compare`, reporting what it does to
+    /// `observer`. Sorters that don't override this only report their
+    /// comparisons.
+    fn sort_observed(
+        &self,
+        slice: &mut [T],
+        compare: &mut dyn FnMut(&T, &T) -> Ordering,
+        observer: &dyn Observer<T>,
+    ) {
+        let probe = Probe::new(slice, observer);
+        self.sort_by_dyn(slice, &mut probe.observe(compare));
+    }
+
+    fn sort(&self, slice: &mut [T])
+    where
+        T: Ord,
+    {
+        self.sort_by_dyn(slice, &mut T::cmp);
+    }
+
+    fn sort_by<F>(&self, slice: &mut [T], mut compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+        Self: Sized,
+    {
+        self.sort_by_dyn(slice, &mut compare);
+    }
+
+    /// Sorts `slice` by the key `f` extracts from each element. `f` is
+    /// called twice per comparison, see [`Sorter::sort_by_cached_key`] for
+    /// expensive keys.
+    fn sort_by_key<K, F>(&self, slice: &mut [T], mut f: F)
+    where
+        K: Ord,
+        F: FnMut(&T) -> K,
+        Self: Sized,
+    {
+        self.sort_by_dyn(slice, &mut |a, b| f(a).cmp(&f(b)));
+    }
+
+    /// Sorts `slice` by the key `f` extracts from each element, calling `f`
+    /// only once per element.
+    ///
+    /// The keys are sorted with this same sorter, so the result is stable
+    /// exactly when the sorter is.
+    #[cfg(feature = "alloc")]
+    fn sort_by_cached_key<K, F>(&self, slice: &mut [T], f: F)
+    where
+        K: Ord,
+        F: FnMut(&T) -> K,
+        Self: Sorter<(K, usize)> + Sized,
+    {
+        let mut indices: alloc::vec::Vec<_> = slice
+            .iter()
+            .map(f)
+            .enumerate()
+            .map(|(i, k)| (k, i))
+            .collect();
+        self.sort_by(&mut indices, |a, b| a.0.cmp(&b.0));
+
+        // `indices[i].1` is where the element that belongs at `i` was
+        // originally. Earlier swaps may have moved it, so follow the chain
+        // of swaps until it's found.
+        for i in 0..slice.len() {
+            let mut index = indices[i].1;
+            while index < i {
+                index = indices[index].1;
+            }
+            indices[i].1 = index;
+            slice.swap(i, index);
+        }
+    }
+}
+
+impl<T, S> Sorter<T> for &S
+where
+    S: Sorter<T> + ?Sized,
+{
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+        (**self).sort_by_dyn(slice, compare);
+    }
+
+    fn properties(&self) -> Properties {
+        (**self).properties()
+    }
+
+    fn sort_observed(
+        &self,
+        slice: &mut [T],
+        compare: &mut dyn FnMut(&T, &T) -> Ordering,
+        observer: &dyn Observer<T>,
+    ) {
+        (**self).sort_observed(slice, compare, observer);
+    }
+
     fn sort(&self, slice: &mut [T])
     where
-        T: Ord;
+        T: Ord,
+    {
+        (**self).sort(slice);
+    }
+}
+
+/// A sorter that orders elements by the bytes of a [`RadixKey`] rather than
+/// by comparing them, the companion of [`Sorter`] for the radix sorts.
+#[cfg(feature = "alloc")]
+pub trait RadixSorter<T> {
+    /// Sorts `slice` by the key `f` extracts from each element.
+    fn sort_by_key<K, F>(&self, slice: &mut [T], f: F)
+    where
+        K: RadixKey,
+        F: FnMut(&T) -> K;
+
+    /// Sorts `slice` by the elements' own keys.
+    fn sort(&self, slice: &mut [T])
+    where
+        T: RadixKey;
+
+    fn properties(&self) -> Properties;
 }
 
+#[cfg(feature = "alloc")]
+pub mod auto_sort;
+#[cfg(feature = "alloc")]
+pub use auto_sort::AutoSort;
+pub mod block_merge_sort;
+pub use block_merge_sort::BlockMergeSort;
 pub mod bubble_sort;
 pub use bubble_sort::BubbleSort;
+#[cfg(feature = "alloc")]
+pub mod bucket_sort;
+#[cfg(feature = "alloc")]
+pub mod collections;
+#[cfg(feature = "alloc")]
+pub use bucket_sort::BucketSort;
+#[cfg(feature = "alloc")]
+pub mod counting_sort;
+#[cfg(feature = "alloc")]
+pub use counting_sort::CountingSort;
+#[cfg(feature = "std")]
+pub mod external;
+#[cfg(feature = "std")]
+pub use external::ExternalSort;
 pub mod heap_sort;
-pub use heap_sort::HeapSort;
+pub use heap_sort::{BottomUpHeapSort, HeapSort};
+#[cfg(feature = "alloc")]
+pub mod inputs;
 pub mod insertion_sort;
+pub mod instrument;
 pub use insertion_sort::InsertionSort;
+pub mod merge;
+#[cfg(feature = "alloc")]
 pub mod merge_sort;
-pub use merge_sort::MergeSort;
+#[cfg(feature = "alloc")]
+pub use merge_sort::{BottomUpMergeSort, MergeSort};
+pub mod network;
+#[cfg(feature = "std")]
+pub mod parallel_sort;
+#[cfg(feature = "std")]
+pub use parallel_sort::{ParallelMergeSort, ParallelQuickSort};
+#[cfg(feature = "alloc")]
+pub mod permutation;
 pub mod quick_sort;
 pub use quick_sort::QuickSort;
+#[cfg(feature = "alloc")]
+pub mod radix_sort;
+#[cfg(feature = "alloc")]
+pub mod registry;
+#[cfg(feature = "alloc")]
+pub use radix_sort::{LsdRadixSort, MsdRadixSort, RadixKey};
+pub mod select;
 pub mod selection_sort;
 pub use selection_sort::SelectionSort;
+pub mod shell_sort;
+pub use shell_sort::{GapSequence, ShellSort};
+pub mod smooth_sort;
+pub use smooth_sort::SmoothSort;
+#[cfg(feature = "alloc")]
+pub mod tim_sort;
+#[cfg(feature = "alloc")]
+pub mod trace;
+#[cfg(feature = "alloc")]
+pub use tim_sort::TimSort;
+#[cfg(feature = "alloc")]
+pub mod weak_heap_sort;
+#[cfg(feature = "alloc")]
+pub use weak_heap_sort::WeakHeapSort;
 
 #[cfg(test)]
 mod tests;
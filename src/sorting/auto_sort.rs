@@ -0,0 +1,134 @@
+use core::cmp::Ordering;
+
+use super::instrument::{Observer, Probe};
+use super::{Complexity, InsertionSort, Properties, QuickSort, Sorter, TimSort};
+
+/// Picks an algorithm for each slice from its length and a quick look at
+/// how sorted it already is.
+///
+/// Short slices are insertion sorted. Longer ones are scanned for descents,
+/// stopping as soon as the slice looks random, so the scan costs a few
+/// comparisons on random input and at most one pass otherwise. Sorted and
+/// strictly descending slices are finished by the scan, mostly sorted ones
+/// go to [`TimSort`] and the rest to [`QuickSort`].
+pub struct AutoSort;
+
+/// Slices up to this length are insertion sorted without scanning them.
+const MAX_INSERTION: usize = 20;
+
+/// What the scan found.
+enum Choice {
+    Sorted,
+    /// Strictly descending, so reversing it sorts it without reordering
+    /// equal elements.
+    Descending,
+    Insertion,
+    Tim,
+    Quick,
+}
+
+fn choose<T, F>(slice: &[T], compare: &mut F) -> Choice
+where
+    F: FnMut(&T, &T) -> Ordering + ?Sized,
+{
+    let len = slice.len();
+    if len <= MAX_INSERTION {
+        return Choice::Insertion;
+    }
+
+    // a slice with no more than this many descents, or this many ascents,
+    // is made of a few long runs
+    let few = len / 16;
+    let (mut descents, mut ascents) = (0, 0);
+    for pair in slice.windows(2) {
+        if compare(&pair[1], &pair[0]) == Ordering::Less {
+            descents += 1;
+        } else {
+            ascents += 1;
+        }
+        if descents > few && ascents > few {
+            return Choice::Quick;
+        }
+    }
+    if descents == 0 {
+        Choice::Sorted
+    } else if ascents == 0 {
+        Choice::Descending
+    } else {
+        Choice::Tim
+    }
+}
+
+impl<T> Sorter<T> for AutoSort {
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+        match choose(slice, compare) {
+            Choice::Sorted => {}
+            Choice::Descending => slice.reverse(),
+            Choice::Insertion => InsertionSort.sort_by_dyn(slice, compare),
+            Choice::Tim => TimSort.sort_by_dyn(slice, compare),
+            Choice::Quick => QuickSort {}.sort_by_dyn(slice, compare),
+        }
+    }
+
+    /// Not stable since random looking slices are quicksorted, and not in
+    /// place since mostly sorted ones are merged.
+    fn properties(&self) -> Properties {
+        Properties {
+            stable: false,
+            in_place: false,
+            requires_clone: false,
+            best: Complexity::Linear,
+            average: Complexity::Linearithmic,
+            worst: Complexity::Linearithmic,
+        }
+    }
+
+    fn sort_observed(
+        &self,
+        slice: &mut [T],
+        compare: &mut dyn FnMut(&T, &T) -> Ordering,
+        observer: &dyn Observer<T>,
+    ) {
+        let probe = Probe::new(slice, observer);
+        let choice = choose(slice, &mut probe.observe(&mut *compare));
+        match choice {
+            Choice::Sorted => {}
+            Choice::Descending => probe.reverse(slice),
+            Choice::Insertion => InsertionSort.sort_observed(slice, compare, observer),
+            Choice::Tim => TimSort.sort_observed(slice, compare, observer),
+            Choice::Quick => QuickSort {}.sort_observed(slice, compare, observer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AutoSort;
+    use crate::sorting::inputs::{self, Distribution};
+    use crate::sorting::instrument::Instrumented;
+    use crate::sorting::Sorter;
+
+    #[test]
+    fn sorts_every_distribution() {
+        for distribution in Distribution::ALL {
+            for len in [0, 1, 15, 100, 5000] {
+                let mut list = distribution.generate(len, 3);
+                AutoSort.sort(&mut list);
+                assert!(list.windows(2).all(|w| w[0] <= w[1]), "{distribution:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn scan_is_cheap() {
+        let n = 10_000;
+        let stats = Instrumented::new(AutoSort).sort(&mut inputs::sorted(n));
+        assert_eq!(stats.comparisons, n - 1);
+        let stats = Instrumented::new(AutoSort).sort(&mut inputs::reversed(n));
+        assert_eq!(stats.comparisons, n - 1);
+
+        let mut list = inputs::nearly_sorted(n, 10, 1);
+        let stats = Instrumented::new(AutoSort).sort(&mut list);
+        assert!(stats.comparisons < 4 * n, "{}", stats.comparisons);
+    }
+}
@@ -0,0 +1,589 @@
+use core::cmp::Ordering;
+
+use super::instrument::{Observer, Probe};
+use super::{insertion_sort, Complexity, Properties, Sorter};
+
+/// Stable merge sort that doesn't allocate, using a block merge along the
+/// lines of GrailSort.
+///
+/// It starts by moving about 2√n distinct elements to the front of the
+/// slice. Half of them are swapped around as a buffer to merge into, the
+/// other half tag blocks of √n elements so that blocks from two runs can be
+/// put into order by their first elements and then merged locally. At the
+/// end the distinct elements are sorted and merged back in. Everything is
+/// moved by swapping, so it runs in O(n log n) time and O(1) space.
+///
+/// Inputs with too few distinct values to fill the buffer are merged with
+/// rotations instead, which still takes O(n log n) comparisons but can take
+/// O(n log² n) swaps, so that's the worst case it reports.
+pub struct BlockMergeSort;
+
+/// Slices up to this length are insertion sorted.
+const MAX_INSERTION: usize = 32;
+/// Length of the runs that are insertion sorted before merging.
+const MIN_RUN: usize = 16;
+
+pub fn sort<T>(slice: &mut [T])
+where
+    T: Ord,
+{
+    sort_by(slice, T::cmp);
+}
+
+pub fn sort_by<T, F>(slice: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    block_merge_sort(slice, &mut compare, &Probe::OFF);
+}
+
+fn block_merge_sort<T, F, O>(v: &mut [T], compare: &mut F, probe: &Probe<T, O>)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    let len = v.len();
+    if len <= MAX_INSERTION {
+        insertion_sort::insertion_sort(v, compare, probe);
+        return;
+    }
+
+    let mut block = 1;
+    while block * block < len {
+        block *= 2;
+    }
+    // a tag for every block, followed by a block sized buffer
+    let tags = len.div_ceil(block);
+    let keys = collect_keys(v, tags + block, compare, probe);
+    if keys < tags + block {
+        rotation_merge_sort(v, compare, probe);
+        return;
+    }
+
+    sort_with_buffer(v, tags, block, compare, probe);
+    insertion_sort::insertion_sort(&mut v[..keys], compare, probe);
+    merge_keys(v, keys, compare, probe);
+}
+
+/// Moves up to `wanted` distinct elements to the front of `v`, in sorted
+/// order, and returns how many it found. Each one is the first of the
+/// elements equal to it, and the rest of the slice keeps its order.
+fn collect_keys<T, F, O>(v: &mut [T], wanted: usize, compare: &mut F, probe: &Probe<T, O>) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    // the keys are kept together at `start..start + found`, and carried
+    // along to wherever the next one is
+    let mut start = 0;
+    let mut found = 1;
+    for i in 1..v.len() {
+        if found == wanted {
+            break;
+        }
+        let pos = start + count_less(v, start..start + found, i, compare);
+        if pos < start + found && compare(&v[i], &v[pos]) == Ordering::Equal {
+            continue;
+        }
+        rotate(&mut v[start..i], found, probe);
+        let pos = pos + (i - start - found);
+        start = i - found;
+        for j in (pos..i).rev() {
+            probe.swap(v, j, j + 1);
+        }
+        found += 1;
+    }
+    rotate(&mut v[..start + found], start, probe);
+    found
+}
+
+/// Sorts everything after the `tags` and `block` elements at the front of
+/// `v`, which have to be distinct, using them as tags and as a buffer.
+fn sort_with_buffer<T, F, O>(
+    v: &mut [T],
+    tags: usize,
+    block: usize,
+    compare: &mut F,
+    probe: &Probe<T, O>,
+) where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    let buf = tags;
+    let data = buf + block;
+    let n = v.len() - data;
+
+    let mut run = MIN_RUN.min(block);
+    for chunk in v[data..].chunks_mut(run) {
+        insertion_sort::insertion_sort(chunk, compare, probe);
+    }
+
+    // Each pass merges pairs of runs left to right, with the buffer moving
+    // along in front of them, and then moves the buffer back. While runs
+    // fit in the buffer they're merged through it directly, longer runs
+    // are merged block by block.
+    while run < n {
+        let mut gap = buf;
+        while v.len() - (gap + block) > run {
+            let rest = v.len() - (gap + block) - run;
+            let b_len = run.min(rest);
+            if run <= block {
+                merge_forward(v, gap, block, run, b_len, compare, probe);
+            } else {
+                merge_blocks(v, gap, block, run, b_len, compare, probe);
+            }
+            gap += run + b_len;
+        }
+        let rest = v.len() - (gap + block);
+        shift_left(v, gap, block, rest, probe);
+        shift_right(v, buf, n, block, probe);
+        run *= 2;
+    }
+}
+
+/// Merges runs `a` and `b`, of `a_len` and `b_len` elements, that follow
+/// the buffer at `gap`, using the tags at the front of `v`. The buffer ends
+/// up after them.
+///
+/// `a` is cut into blocks, and so is `b` apart from its last `b_len % block`
+/// elements. The blocks are sorted by their first elements, with the tags
+/// breaking ties so blocks from `a` come first and blocks from the same run
+/// stay in order. After that each element is at most a block away from
+/// where it belongs, and merging neighbouring blocks finishes the job.
+fn merge_blocks<T, F, O>(
+    v: &mut [T],
+    gap: usize,
+    block: usize,
+    a_len: usize,
+    b_len: usize,
+    compare: &mut F,
+    probe: &Probe<T, O>,
+) where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    let a = gap + block;
+    if compare(&v[a + a_len - 1], &v[a + a_len]) != Ordering::Greater {
+        shift_left(v, gap, block, a_len + b_len, probe);
+        return;
+    }
+
+    let blocks = (a_len + b_len) / block;
+    // tags before this one belong to blocks of `a`
+    let mut mid = a_len / block;
+    let first = |i: usize| a + i * block;
+    for i in 0..blocks {
+        let mut min = i;
+        for j in i + 1..blocks {
+            let order = compare(&v[first(j)], &v[first(min)]);
+            if order == Ordering::Less
+                || order == Ordering::Equal && compare(&v[j], &v[min]) == Ordering::Less
+            {
+                min = j;
+            }
+        }
+        if min != i {
+            swap_blocks(v, first(i), first(min), block, probe);
+            probe.swap(v, i, min);
+            if mid == i {
+                mid = min;
+            } else if mid == min {
+                mid = i;
+            }
+        }
+    }
+
+    // The buffer is at `out`, followed by the `rest` elements of the last
+    // block(s) that aren't merged yet, which all come from the same run.
+    let mut out = gap;
+    let mut rest = block;
+    let mut from_a = compare(&v[0], &v[mid]) == Ordering::Less;
+    for i in 1..blocks {
+        let next_from_a = compare(&v[i], &v[mid]) == Ordering::Less;
+        if next_from_a == from_a {
+            shift_left(v, out, block, rest, probe);
+            out += rest;
+            rest = block;
+        } else {
+            let (merged, left, flipped) = merge_local(v, out, block, rest, from_a, compare, probe);
+            out += merged;
+            rest = left;
+            from_a ^= flipped;
+        }
+    }
+    shift_left(v, out, block, rest, probe);
+    out += rest;
+
+    let tail = a_len + b_len - blocks * block;
+    if tail > 0 {
+        merge_backward(v, gap, out - gap, block, tail, compare, probe);
+    }
+    insertion_sort::insertion_sort(&mut v[..blocks], compare, probe);
+}
+
+/// Merges the `x_len` elements after the buffer at `gap` with the next
+/// `block`, until either runs out. Ties go to the first unless `x_first` is
+/// unset. Returns how many were merged, and how many are left, moved to
+/// just after the buffer, and whether they're from the second.
+fn merge_local<T, F, O>(
+    v: &mut [T],
+    gap: usize,
+    block: usize,
+    x_len: usize,
+    x_first: bool,
+    compare: &mut F,
+    probe: &Probe<T, O>,
+) -> (usize, usize, bool)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    let (mut out, mut x) = (gap, gap + block);
+    let x_end = x + x_len;
+    let (mut y, y_end) = (x_end, x_end + block);
+    while x < x_end && y < y_end {
+        let order = compare(&v[x], &v[y]);
+        if order == Ordering::Less || x_first && order == Ordering::Equal {
+            probe.swap(v, out, x);
+            x += 1;
+        } else {
+            probe.swap(v, out, y);
+            y += 1;
+        }
+        out += 1;
+    }
+
+    if x < x_end {
+        // the second block ran out, so `out == x` and the block's old place
+        // holds the buffer
+        let left = x_end - x;
+        for i in (0..left).rev() {
+            probe.swap(v, x + i, x + block + i);
+        }
+        (out - gap, left, false)
+    } else {
+        (out - gap, y_end - y, true)
+    }
+}
+
+/// Merges the `x_len` and `y_len` elements after the buffer at `gap`,
+/// moving the buffer after them. The buffer can't be shorter than `y_len`.
+fn merge_forward<T, F, O>(
+    v: &mut [T],
+    gap: usize,
+    block: usize,
+    x_len: usize,
+    y_len: usize,
+    compare: &mut F,
+    probe: &Probe<T, O>,
+) where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    let (mut out, mut x) = (gap, gap + block);
+    let x_end = x + x_len;
+    let (mut y, y_end) = (x_end, x_end + y_len);
+    if compare(&v[x_end - 1], &v[y]) != Ordering::Greater {
+        shift_left(v, gap, block, x_len + y_len, probe);
+        return;
+    }
+
+    while x < x_end && y < y_end {
+        if compare(&v[y], &v[x]) == Ordering::Less {
+            probe.swap(v, out, y);
+            y += 1;
+        } else {
+            probe.swap(v, out, x);
+            x += 1;
+        }
+        out += 1;
+    }
+    while x < x_end {
+        probe.swap(v, out, x);
+        (out, x) = (out + 1, x + 1);
+    }
+    while y < y_end {
+        probe.swap(v, out, y);
+        (out, y) = (out + 1, y + 1);
+    }
+}
+
+/// Merges the `x_len` elements at `x_start` with the `y_len` elements after
+/// the buffer that follows them, moving the buffer to the end.
+fn merge_backward<T, F, O>(
+    v: &mut [T],
+    x_start: usize,
+    x_len: usize,
+    block: usize,
+    y_len: usize,
+    compare: &mut F,
+    probe: &Probe<T, O>,
+) where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    let mut out = x_start + x_len + y_len;
+    let mut x = x_start + x_len;
+    let y_start = x + block;
+    let mut y = y_start + y_len;
+    while y > y_start {
+        out -= 1;
+        if x > x_start && compare(&v[x - 1], &v[y - 1]) == Ordering::Greater {
+            x -= 1;
+            probe.swap(v, out, x);
+        } else {
+            y -= 1;
+            probe.swap(v, out, y);
+        }
+    }
+}
+
+/// Merges the sorted `keys` at the front of `v` into the sorted rest, by
+/// rotating them past the elements that go before them. Takes O(n + k²)
+/// time for `k` keys.
+fn merge_keys<T, F, O>(v: &mut [T], keys: usize, compare: &mut F, probe: &Probe<T, O>)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    let (mut start, mut len) = (0, keys);
+    while len > 0 && start + len < v.len() {
+        let end = start + len;
+        let skip = count_less(v, end..v.len(), start, compare);
+        rotate(&mut v[start..end + skip], len, probe);
+        start += skip;
+        if start + len == v.len() {
+            break;
+        }
+        // keys that aren't greater than the next element are in place
+        let done = count_not_greater(v, start..start + len, start + len, compare);
+        start += done;
+        len -= done;
+    }
+}
+
+/// Bottom up merge sort that merges by rotating, for when there's no
+/// buffer.
+fn rotation_merge_sort<T, F, O>(v: &mut [T], compare: &mut F, probe: &Probe<T, O>)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    for chunk in v.chunks_mut(MIN_RUN) {
+        insertion_sort::insertion_sort(chunk, compare, probe);
+    }
+    let mut run = MIN_RUN;
+    while run < v.len() {
+        for pair in v.chunks_mut(2 * run) {
+            if pair.len() > run {
+                sym_merge(pair, run, compare, probe);
+            }
+        }
+        run *= 2;
+    }
+}
+
+/// Merges `v[..mid]` and `v[mid..]` in place with Kim and Kutzner's
+/// SymMerge, which splits both around a rotation and recurses.
+pub(super) fn sym_merge<T, F, O>(v: &mut [T], mid: usize, compare: &mut F, probe: &Probe<T, O>)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    let len = v.len();
+    if mid == 0 || mid == len || compare(&v[mid - 1], &v[mid]) != Ordering::Greater {
+        return;
+    }
+    if mid == 1 {
+        let pos = 1 + count_less(v, 1..len, 0, compare);
+        for i in 1..pos {
+            probe.swap(v, i - 1, i);
+        }
+        return;
+    }
+    if len - mid == 1 {
+        let pos = count_not_greater(v, 0..mid, mid, compare);
+        for i in (pos..mid).rev() {
+            probe.swap(v, i, i + 1);
+        }
+        return;
+    }
+
+    // Find the split where the start of the left side is swapped with the
+    // end of the right, symmetrically around the middle of the slice.
+    let half = len / 2;
+    let n = half + mid;
+    let (mut lo, mut hi) = if mid > half {
+        (n - len, half)
+    } else {
+        (0, mid)
+    };
+    let last = n - 1;
+    while lo < hi {
+        let c = (lo + hi) / 2;
+        if compare(&v[last - c], &v[c]) != Ordering::Less {
+            lo = c + 1;
+        } else {
+            hi = c;
+        }
+    }
+    let start = lo;
+    let end = n - start;
+    if start < mid && mid < end {
+        rotate(&mut v[start..end], mid - start, probe);
+    }
+    if start > 0 && start < half {
+        sym_merge(&mut v[..half], start, compare, probe);
+    }
+    if half < end && end < len {
+        sym_merge(&mut v[half..], end - half, compare, probe);
+    }
+}
+
+/// The number of elements in `range` that are less than `v[x]`.
+fn count_less<T, F>(v: &[T], range: core::ops::Range<usize>, x: usize, compare: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    v[range].partition_point(|y| compare(y, &v[x]) == Ordering::Less)
+}
+
+/// The number of elements in `range` that aren't greater than `v[x]`.
+fn count_not_greater<T, F>(
+    v: &[T],
+    range: core::ops::Range<usize>,
+    x: usize,
+    compare: &mut F,
+) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    v[range].partition_point(|y| compare(y, &v[x]) != Ordering::Greater)
+}
+
+/// Rotates `v` left by `mid` with three reversals.
+fn rotate<T, O>(v: &mut [T], mid: usize, probe: &Probe<T, O>)
+where
+    O: Observer<T> + ?Sized,
+{
+    if mid == 0 || mid == v.len() {
+        return;
+    }
+    probe.reverse(&mut v[..mid]);
+    probe.reverse(&mut v[mid..]);
+    probe.reverse(v);
+}
+
+fn swap_blocks<T, O>(v: &mut [T], a: usize, b: usize, len: usize, probe: &Probe<T, O>)
+where
+    O: Observer<T> + ?Sized,
+{
+    for i in 0..len {
+        probe.swap(v, a + i, b + i);
+    }
+}
+
+/// Moves the `len` elements after the buffer at `gap` in front of it.
+fn shift_left<T, O>(v: &mut [T], gap: usize, block: usize, len: usize, probe: &Probe<T, O>)
+where
+    O: Observer<T> + ?Sized,
+{
+    for i in 0..len {
+        probe.swap(v, gap + i, gap + block + i);
+    }
+}
+
+/// Moves the `len` elements at `start` after the buffer that follows them.
+fn shift_right<T, O>(v: &mut [T], start: usize, len: usize, block: usize, probe: &Probe<T, O>)
+where
+    O: Observer<T> + ?Sized,
+{
+    for i in (0..len).rev() {
+        probe.swap(v, start + i, start + block + i);
+    }
+}
+
+impl<T> Sorter<T> for BlockMergeSort {
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+        sort_by(slice, compare);
+    }
+
+    fn properties(&self) -> Properties {
+        Properties {
+            stable: true,
+            in_place: true,
+            requires_clone: false,
+            best: Complexity::Linearithmic,
+            average: Complexity::Linearithmic,
+            worst: Complexity::LinearithmicSquared,
+        }
+    }
+
+    fn sort_observed(
+        &self,
+        slice: &mut [T],
+        compare: &mut dyn FnMut(&T, &T) -> Ordering,
+        observer: &dyn Observer<T>,
+    ) {
+        let probe = Probe::new(slice, observer);
+        block_merge_sort(slice, &mut probe.observe(compare), &probe);
+    }
+
+    fn sort(&self, slice: &mut [T])
+    where
+        T: Ord,
+    {
+        sort(slice);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use crate::sorting::inputs;
+    use crate::sorting::instrument::Instrumented;
+    use crate::sorting::BlockMergeSort;
+    use alloc::vec::Vec;
+
+    // Sorting by key alone, the indices show whether equal keys kept their
+    // order.
+    fn check(keys: Vec<u64>) {
+        let mut list: Vec<_> = keys.into_iter().zip(0..).collect();
+        let mut expected = list.clone();
+        expected.sort_by_key(|x| x.0);
+        super::sort_by(&mut list, |a: &(u64, usize), b| a.0.cmp(&b.0));
+        assert_eq!(list, expected);
+    }
+
+    #[test]
+    fn stable() {
+        for len in [0, 1, 2, 31, 32, 33, 64, 100, 1000, 4097, 20_000] {
+            for distribution in inputs::Distribution::ALL {
+                check(distribution.generate(len, 1));
+            }
+            check(inputs::few_unique(len, 300, 2));
+            check(inputs::random(len, 3).iter().map(|x| x % 1000).collect());
+        }
+    }
+
+    #[test]
+    fn without_enough_distinct_keys() {
+        for unique in [1, 2, 3, 10, 100, 250] {
+            check(inputs::few_unique(50_000, unique, unique));
+        }
+    }
+
+    /// With fewer than 2√n distinct keys there's no buffer, and the
+    /// rotation merges move each element O(log² n) times.
+    #[test]
+    fn moves_without_a_buffer() {
+        let n = 16_000;
+        let log = (n as f64).log2();
+        for unique in [8, 64, 200] {
+            let mut list = inputs::few_unique(n, unique, 4);
+            let stats = Instrumented::new(BlockMergeSort).sort(&mut list);
+            assert!(list.windows(2).all(|w| w[0] <= w[1]));
+            let moves = (stats.swaps + stats.writes) as f64;
+            assert!(moves < 0.3 * n as f64 * log * log, "{unique}: {moves}");
+        }
+    }
+}
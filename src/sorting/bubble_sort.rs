@@ -1,17 +1,35 @@
-use super::Sorter;
+use core::cmp::Ordering;
+
+use super::instrument::{Observer, Probe};
+use super::{Complexity, Properties, Sorter};
 
 pub struct BubbleSort;
 
 pub fn sort<T>(slice: &mut [T])
 where
     T: Ord,
+{
+    sort_by(slice, T::cmp);
+}
+
+pub fn sort_by<T, F>(slice: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    bubble_sort(slice, &mut compare, &Probe::OFF);
+}
+
+fn bubble_sort<T, F, O>(slice: &mut [T], compare: &mut F, probe: &Probe<T, O>)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
 {
     let mut swapped = true;
     while swapped {
         swapped = false;
         for i in 1..slice.len() {
-            if slice[i] < slice[i - 1] {
-                slice.swap(i, i - 1);
+            if compare(&slice[i], &slice[i - 1]) == Ordering::Less {
+                probe.swap(slice, i, i - 1);
                 swapped = true;
             }
         }
@@ -19,6 +37,31 @@ where
 }
 
 impl<T> Sorter<T> for BubbleSort {
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+        sort_by(slice, compare);
+    }
+
+    fn properties(&self) -> Properties {
+        Properties {
+            stable: true,
+            in_place: true,
+            requires_clone: false,
+            best: Complexity::Linear,
+            average: Complexity::Quadratic,
+            worst: Complexity::Quadratic,
+        }
+    }
+
+    fn sort_observed(
+        &self,
+        slice: &mut [T],
+        compare: &mut dyn FnMut(&T, &T) -> Ordering,
+        observer: &dyn Observer<T>,
+    ) {
+        let probe = Probe::new(slice, observer);
+        bubble_sort(slice, &mut probe.observe(compare), &probe);
+    }
+
     fn sort(&self, slice: &mut [T])
     where
         T: Ord,
@@ -0,0 +1,165 @@
+use core::fmt;
+
+use super::counting_sort;
+use super::Sorter;
+
+/// Sorts floats by scattering them into equal width buckets between the
+/// smallest and largest value, and sorting each bucket with `inner`. Runs in
+/// linear time on average when the values are roughly uniform.
+pub struct BucketSort<S> {
+    /// How many buckets to use, 0 meaning one per element.
+    pub buckets: usize,
+    pub inner: S,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum BucketSortError {
+    /// The key of the element at `position` is NaN or infinite.
+    NotFinite { position: usize },
+    /// There isn't enough memory to count this many buckets.
+    TooManyBuckets(usize),
+}
+
+impl fmt::Display for BucketSortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BucketSortError::NotFinite { position } => {
+                write!(f, "element {position} has a key that is NaN or infinite")
+            }
+            BucketSortError::TooManyBuckets(buckets) => {
+                write!(f, "cannot allocate counts for {buckets} buckets")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BucketSortError {}
+
+impl<S> BucketSort<S> {
+    pub fn new(inner: S) -> Self {
+        Self { buckets: 0, inner }
+    }
+
+    pub fn try_sort<T>(&self, slice: &mut [T]) -> Result<(), BucketSortError>
+    where
+        T: Copy + Into<f64>,
+        S: Sorter<T>,
+    {
+        self.try_sort_by_key(slice, |x| (*x).into())
+    }
+
+    /// Sorts `slice` by the key `f` extracts from each element. If any key
+    /// isn't finite, or the buckets can't be allocated, the slice is left
+    /// untouched.
+    pub fn try_sort_by_key<T, F>(&self, slice: &mut [T], mut f: F) -> Result<(), BucketSortError>
+    where
+        F: FnMut(&T) -> f64,
+        S: Sorter<T>,
+    {
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+        for (position, x) in slice.iter().enumerate() {
+            let key = f(x);
+            if !key.is_finite() {
+                return Err(BucketSortError::NotFinite { position });
+            }
+            min = min.min(key);
+            max = max.max(key);
+        }
+        if slice.len() < 2 {
+            return Ok(());
+        }
+
+        let buckets = if self.buckets == 0 {
+            slice.len()
+        } else {
+            self.buckets
+        };
+        // `max - min` can overflow to infinity, so scale both ends first
+        let scale = buckets as f64 / (max / 2.0 - min / 2.0) / 2.0;
+        let bucket = |key: f64| {
+            let b = ((key - min) * scale) as usize;
+            // also catches NaN from a zero width range, which casts to 0
+            b.min(buckets - 1)
+        };
+
+        // the indices are always in range, so the only way this fails is
+        // running out of memory for the counts
+        counting_sort::sort_by_index(slice, buckets, |x| bucket(f(x)))
+            .map_err(|_| BucketSortError::TooManyBuckets(buckets))?;
+
+        let mut start = 0;
+        while start < slice.len() {
+            let b = bucket(f(&slice[start]));
+            let mut end = start + 1;
+            while end < slice.len() && bucket(f(&slice[end])) == b {
+                end += 1;
+            }
+            self.inner
+                .sort_by_dyn(&mut slice[start..end], &mut |a, b| f(a).total_cmp(&f(b)));
+            start = end;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BucketSort, BucketSortError};
+    use crate::sorting::{InsertionSort, QuickSort};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn uniform_floats() {
+        let input: Vec<f64> = (0..5000)
+            .map(|i| ((i * 7919) % 5003) as f64 / 5003.0)
+            .collect();
+        let mut expected = input.clone();
+        expected.sort_by(f64::total_cmp);
+
+        let mut list = input.clone();
+        BucketSort::new(InsertionSort).try_sort(&mut list).unwrap();
+        assert_eq!(list, expected);
+
+        let mut list = input;
+        let sorter = BucketSort {
+            buckets: 16,
+            inner: QuickSort {},
+        };
+        sorter.try_sort(&mut list).unwrap();
+        assert_eq!(list, expected);
+
+        let mut extremes = [f64::MAX, -f64::MAX, 0.0, 1.0, -1.0, f64::MAX];
+        BucketSort::new(InsertionSort)
+            .try_sort(&mut extremes)
+            .unwrap();
+        assert_eq!(extremes, [-f64::MAX, -1.0, 0.0, 1.0, f64::MAX, f64::MAX]);
+
+        let mut same = [2.5f32; 10];
+        BucketSort::new(InsertionSort).try_sort(&mut same).unwrap();
+    }
+
+    #[test]
+    fn rejects_nan() {
+        let mut list = [0.5, 0.25, f64::NAN, 0.75];
+        assert_eq!(
+            BucketSort::new(InsertionSort).try_sort(&mut list),
+            Err(BucketSortError::NotFinite { position: 2 })
+        );
+    }
+
+    #[test]
+    fn too_many_buckets() {
+        let mut list = [0.5, 0.25, 0.75];
+        let sorter = BucketSort {
+            buckets: usize::MAX,
+            inner: InsertionSort,
+        };
+        assert_eq!(
+            sorter.try_sort(&mut list),
+            Err(BucketSortError::TooManyBuckets(usize::MAX))
+        );
+        assert_eq!(list, [0.5, 0.25, 0.75]);
+    }
+}
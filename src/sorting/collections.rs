@@ -0,0 +1,247 @@
+//! Sorting collections that aren't slices: linked lists, deques and
+//! iterators.
+
+use alloc::collections::{LinkedList, VecDeque};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::mem;
+
+use super::Sorter;
+
+/// Enough piles for any list that fits in memory, pile `i` holding about
+/// `2^i` runs.
+const PILES: usize = usize::BITS as usize;
+
+/// Sorts `list` with a stable natural merge sort.
+///
+/// The list is cut into its ascending runs, which are merged by splitting
+/// and appending nodes, so no node is allocated or freed and no element is
+/// moved. A sorted list takes `n - 1` comparisons.
+///
+/// ```
+/// use std::collections::LinkedList;
+/// use chonky_structures::sorting::collections::sort_list;
+///
+/// let mut list = LinkedList::from([3, 1, 2]);
+/// sort_list(&mut list);
+/// assert!(list.into_iter().eq([1, 2, 3]));
+/// ```
+pub fn sort_list<T>(list: &mut LinkedList<T>)
+where
+    T: Ord,
+{
+    sort_list_by(list, T::cmp);
+}
+
+pub fn sort_list_by<T, F>(list: &mut LinkedList<T>, mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    // pile `i` comes before every pile below it in the list, like the
+    // digits of a binary counter of runs
+    let mut piles: [LinkedList<T>; PILES] = core::array::from_fn(|_| LinkedList::new());
+    while !list.is_empty() {
+        let mut run = take_run(list, &mut compare);
+        for pile in piles.iter_mut() {
+            if pile.is_empty() {
+                mem::swap(pile, &mut run);
+                break;
+            }
+            run = merge_lists(mem::take(pile), run, &mut compare);
+        }
+    }
+    for pile in piles.iter_mut() {
+        let merged = merge_lists(mem::take(pile), mem::take(list), &mut compare);
+        *list = merged;
+    }
+}
+
+/// Splits the ascending run off the front of `list`.
+fn take_run<T, F>(list: &mut LinkedList<T>, compare: &mut F) -> LinkedList<T>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let mut iter = list.iter();
+    let mut len = 1;
+    if let Some(mut prev) = iter.next() {
+        for next in iter {
+            if compare(next, prev) == Ordering::Less {
+                break;
+            }
+            prev = next;
+            len += 1;
+        }
+    }
+    let rest = list.split_off(len);
+    mem::replace(list, rest)
+}
+
+/// Merges two sorted lists, `left` winning ties, by moving whole stretches
+/// of nodes from one to the other.
+fn merge_lists<T, F>(
+    mut left: LinkedList<T>,
+    mut right: LinkedList<T>,
+    compare: &mut F,
+) -> LinkedList<T>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let mut merged = LinkedList::new();
+    while let Some(first) = right.front() {
+        let take = left
+            .iter()
+            .take_while(|x| compare(first, x) != Ordering::Less)
+            .count();
+        move_front(&mut left, take, &mut merged);
+
+        let Some(first) = left.front() else {
+            break;
+        };
+        let take = right
+            .iter()
+            .take_while(|x| compare(x, first) == Ordering::Less)
+            .count();
+        move_front(&mut right, take, &mut merged);
+    }
+    merged.append(&mut left);
+    merged.append(&mut right);
+    merged
+}
+
+/// Moves the first `len` nodes of `from` to the back of `to`.
+fn move_front<T>(from: &mut LinkedList<T>, len: usize, to: &mut LinkedList<T>) {
+    let rest = from.split_off(len);
+    to.append(from);
+    *from = rest;
+}
+
+/// Sorts `deque` with `sorter`, after moving its elements into one
+/// contiguous slice.
+///
+/// ```
+/// use std::collections::VecDeque;
+/// use chonky_structures::sorting::{collections::sort_deque, HeapSort};
+///
+/// let mut deque = VecDeque::from([2, 3]);
+/// deque.push_front(1);
+/// deque.push_front(4);
+/// sort_deque(&mut deque, &HeapSort);
+/// assert_eq!(deque, [1, 2, 3, 4]);
+/// ```
+pub fn sort_deque<T, S>(deque: &mut VecDeque<T>, sorter: &S)
+where
+    T: Ord,
+    S: Sorter<T> + ?Sized,
+{
+    sorter.sort(deque.make_contiguous());
+}
+
+pub fn sort_deque_by<T, S, F>(deque: &mut VecDeque<T>, sorter: &S, mut compare: F)
+where
+    S: Sorter<T> + ?Sized,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    sorter.sort_by_dyn(deque.make_contiguous(), &mut compare);
+}
+
+/// Collecting an iterator into sorted order with a chosen [`Sorter`].
+///
+/// ```
+/// use chonky_structures::sorting::{collections::SortedIterator, MergeSort};
+///
+/// let words = ["bb", "a", "ccc", "dd"];
+/// let by_len: Vec<_> = words.iter().sorted_by_key_with(&MergeSort {}, |w| w.len()).collect();
+/// assert_eq!(by_len, [&"a", &"bb", &"dd", &"ccc"]);
+/// ```
+pub trait SortedIterator: Iterator + Sized {
+    fn sorted_with<S>(self, sorter: &S) -> alloc::vec::IntoIter<Self::Item>
+    where
+        Self::Item: Ord,
+        S: Sorter<Self::Item> + ?Sized,
+    {
+        let mut items: Vec<_> = self.collect();
+        sorter.sort(&mut items);
+        items.into_iter()
+    }
+
+    fn sorted_by_with<S, F>(self, sorter: &S, mut compare: F) -> alloc::vec::IntoIter<Self::Item>
+    where
+        S: Sorter<Self::Item> + ?Sized,
+        F: FnMut(&Self::Item, &Self::Item) -> Ordering,
+    {
+        let mut items: Vec<_> = self.collect();
+        sorter.sort_by_dyn(&mut items, &mut compare);
+        items.into_iter()
+    }
+
+    /// Sorts by the key `f` extracts from each item, calling `f` twice per
+    /// comparison.
+    fn sorted_by_key_with<S, K, F>(self, sorter: &S, mut f: F) -> alloc::vec::IntoIter<Self::Item>
+    where
+        S: Sorter<Self::Item> + ?Sized,
+        K: Ord,
+        F: FnMut(&Self::Item) -> K,
+    {
+        self.sorted_by_with(sorter, |a, b| f(a).cmp(&f(b)))
+    }
+}
+
+impl<I: Iterator> SortedIterator for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorting::inputs::{self, Distribution};
+    use crate::sorting::{MergeSort, QuickSort};
+
+    #[test]
+    fn lists() {
+        for distribution in Distribution::ALL {
+            for len in [0, 1, 2, 17, 1000] {
+                let list = distribution.generate(len, 5);
+                let mut linked: LinkedList<_> = list.iter().copied().collect();
+                sort_list(&mut linked);
+                let mut expected = list;
+                expected.sort();
+                assert!(linked.into_iter().eq(expected), "{distribution:?} {len}");
+            }
+        }
+    }
+
+    #[test]
+    fn lists_are_stable() {
+        let keys = inputs::few_unique(2000, 10, 6);
+        let mut linked: LinkedList<_> = keys.iter().copied().zip(0..).collect();
+        sort_list_by(&mut linked, |a, b| a.0.cmp(&b.0));
+        let sorted: Vec<_> = linked.into_iter().collect();
+        assert!(sorted.windows(2).all(|w| w[0] < w[1]));
+
+        let mut comparisons = 0;
+        let mut linked: LinkedList<_> = inputs::sorted(1000).into_iter().collect();
+        sort_list_by(&mut linked, |a, b| {
+            comparisons += 1;
+            a.cmp(b)
+        });
+        assert_eq!(comparisons, 999);
+    }
+
+    #[test]
+    fn deques_and_iterators() {
+        let list = inputs::random(300, 7);
+        let mut expected = list.clone();
+        expected.sort();
+
+        let mut deque: VecDeque<_> = list.iter().copied().collect();
+        deque.rotate_right(100);
+        sort_deque(&mut deque, &QuickSort {});
+        assert!(deque.iter().eq(&expected));
+        sort_deque_by(&mut deque, &MergeSort {}, |a, b| b.cmp(a));
+        assert!(deque.iter().eq(expected.iter().rev()));
+
+        assert!(list.iter().sorted_with(&QuickSort {}).eq(&expected));
+        let by_key = list
+            .iter()
+            .sorted_by_key_with(&MergeSort {}, |x| core::cmp::Reverse(**x));
+        assert!(by_key.eq(expected.iter().rev()));
+    }
+}
@@ -0,0 +1,161 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Stable sort for keys drawn from a small range, running in O(n + k) time
+/// for `n` elements and `k` possible keys.
+///
+/// `index` maps each element to its key's position in `0..range`, so for
+/// example a percentage can be sorted with a range of 101 and `|p| *p as
+/// usize`.
+pub struct CountingSort<F> {
+    pub range: usize,
+    pub index: F,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CountingSortError {
+    /// The element at `position` mapped to `index`, which isn't less than
+    /// `range`.
+    IndexOutOfRange {
+        position: usize,
+        index: usize,
+        range: usize,
+    },
+    /// Not enough memory for a count per possible key.
+    RangeTooLarge(usize),
+}
+
+impl fmt::Display for CountingSortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CountingSortError::IndexOutOfRange {
+                position,
+                index,
+                range,
+            } => write!(
+                f,
+                "element {position} has index {index}, outside of the range 0..{range}"
+            ),
+            CountingSortError::RangeTooLarge(range) => {
+                write!(f, "cannot allocate counts for a range of {range} keys")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CountingSortError {}
+
+impl<F> CountingSort<F> {
+    pub fn new(range: usize, index: F) -> Self {
+        Self { range, index }
+    }
+
+    /// Sorts `slice` by index. If any index is out of range the slice is
+    /// left untouched.
+    pub fn try_sort<T>(&self, slice: &mut [T]) -> Result<(), CountingSortError>
+    where
+        F: Fn(&T) -> usize,
+    {
+        sort_by_index(slice, self.range, &self.index)
+    }
+}
+
+/// Sorts `slice` stably by `index`, which must map every element into
+/// `0..range`. If it doesn't the slice is left untouched.
+pub fn sort_by_index<T, F>(
+    slice: &mut [T],
+    range: usize,
+    mut index: F,
+) -> Result<(), CountingSortError>
+where
+    F: FnMut(&T) -> usize,
+{
+    let mut counts = Vec::new();
+    counts
+        .try_reserve_exact(range)
+        .map_err(|_| CountingSortError::RangeTooLarge(range))?;
+    counts.resize(range, 0);
+
+    let mut indices = Vec::with_capacity(slice.len());
+    for (position, x) in slice.iter().enumerate() {
+        let i = index(x);
+        if i >= range {
+            return Err(CountingSortError::IndexOutOfRange {
+                position,
+                index: i,
+                range,
+            });
+        }
+        counts[i] += 1;
+        indices.push(i);
+    }
+
+    // turn the counts into where each index's elements start
+    let mut sum = 0;
+    for count in counts.iter_mut() {
+        sum += *count;
+        *count = sum - *count;
+    }
+
+    // and then every element's final position, in order so equal elements
+    // stay in order
+    for i in indices.iter_mut() {
+        let dest = counts[*i];
+        counts[*i] += 1;
+        *i = dest;
+    }
+
+    permute(slice, &mut indices);
+    Ok(())
+}
+
+/// Moves the element at `i` to `dest[i]` for every `i`, following each cycle
+/// of the permutation with swaps. Leaves `dest` as the identity.
+fn permute<T>(slice: &mut [T], dest: &mut [usize]) {
+    for i in 0..slice.len() {
+        while dest[i] != i {
+            let j = dest[i];
+            slice.swap(i, j);
+            dest.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CountingSort, CountingSortError};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn sorts_stably() {
+        let mut pairs: Vec<(u8, usize)> = (0..1000).map(|i| ((i * 37 % 101) as u8, i)).collect();
+        let mut expected = pairs.clone();
+        expected.sort_by_key(|p| p.0);
+
+        let sorter = CountingSort::new(101, |p: &(u8, usize)| p.0 as usize);
+        assert_eq!(sorter.try_sort(&mut pairs), Ok(()));
+        assert_eq!(pairs, expected);
+    }
+
+    #[test]
+    fn rejects_out_of_range() {
+        let mut list = [3, 1, 4, 1, 5, 9, 2, 6];
+        let sorter = CountingSort::new(8, |x: &i32| *x as usize);
+        assert_eq!(
+            sorter.try_sort(&mut list),
+            Err(CountingSortError::IndexOutOfRange {
+                position: 5,
+                index: 9,
+                range: 8
+            })
+        );
+        assert_eq!(list, [3, 1, 4, 1, 5, 9, 2, 6]);
+
+        let sorter = CountingSort::new(usize::MAX, |x: &i32| *x as usize);
+        assert_eq!(
+            sorter.try_sort(&mut list),
+            Err(CountingSortError::RangeTooLarge(usize::MAX))
+        );
+    }
+}
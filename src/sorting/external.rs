@@ -0,0 +1,444 @@
+//! Sorting inputs that don't fit in memory.
+//!
+//! [`ExternalSort`] reads records until its memory budget is used up, sorts
+//! them and spills them to a temporary file as a run, and repeats until the
+//! input runs out. The runs are then merged, `fan_in` at a time, until
+//! there are few enough left to merge straight into the output.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::mem;
+use core::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::process;
+
+use super::instrument::Probe;
+use super::{heap_sort, Sorter};
+
+/// Reads and writes the records an [`ExternalSort`] sorts.
+pub trait Codec {
+    type Record;
+
+    /// Reads the next record, or returns `None` at the end of the input.
+    fn decode<R: BufRead>(&self, reader: &mut R) -> io::Result<Option<Self::Record>>;
+
+    fn encode<W: Write>(&self, record: &Self::Record, writer: &mut W) -> io::Result<()>;
+
+    /// Roughly how many bytes `record` takes up in memory, which is what's
+    /// counted against the memory budget.
+    fn memory(&self, record: &Self::Record) -> usize {
+        let _ = record;
+        mem::size_of::<Self::Record>()
+    }
+}
+
+/// Newline separated UTF-8 lines. The newline isn't part of the record, and
+/// one is written after every record.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Lines;
+
+impl Codec for Lines {
+    type Record = String;
+
+    fn decode<R: BufRead>(&self, reader: &mut R) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+        }
+        Ok(Some(line))
+    }
+
+    fn encode<W: Write>(&self, record: &String, writer: &mut W) -> io::Result<()> {
+        writer.write_all(record.as_bytes())?;
+        writer.write_all(b"\n")
+    }
+
+    fn memory(&self, record: &String) -> usize {
+        mem::size_of::<String>() + record.capacity()
+    }
+}
+
+/// Arbitrary bytes, each record preceded by its length as a little endian
+/// `u64`.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct LengthPrefixed;
+
+impl Codec for LengthPrefixed {
+    type Record = Vec<u8>;
+
+    fn decode<R: BufRead>(&self, reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+        if reader.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+        let mut len = [0; 8];
+        reader.read_exact(&mut len)?;
+        let len = usize::try_from(u64::from_le_bytes(len))
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "record too long"))?;
+
+        let mut record = Vec::new();
+        reader.take(len as u64).read_to_end(&mut record)?;
+        if record.len() != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(Some(record))
+    }
+
+    fn encode<W: Write>(&self, record: &Vec<u8>, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&(record.len() as u64).to_le_bytes())?;
+        writer.write_all(record)
+    }
+
+    fn memory(&self, record: &Vec<u8>) -> usize {
+        mem::size_of::<Vec<u8>>() + record.capacity()
+    }
+}
+
+/// Sorts the records `codec` reads from an input into an output, keeping at
+/// most about `memory` bytes of records in memory at once.
+///
+/// Runs are merged in order and ties go to the earlier run, so the sort is
+/// stable when `sorter` is.
+pub struct ExternalSort<S, C> {
+    pub sorter: S,
+    pub codec: C,
+    /// The memory budget in bytes, as measured by [`Codec::memory`].
+    pub memory: usize,
+    /// How many runs to merge at once, at least 2.
+    pub fan_in: usize,
+    /// Where to put the runs. On Unix only the owner can read them, elsewhere
+    /// they get the directory's default permissions, so a shared directory
+    /// can expose the data being sorted.
+    pub temp_dir: PathBuf,
+}
+
+impl<S, C: Codec> ExternalSort<S, C> {
+    /// A sort with a 64 MiB memory budget that merges 16 runs at once, and
+    /// keeps its runs in the system's temporary directory.
+    pub fn new(sorter: S, codec: C) -> Self {
+        Self {
+            sorter,
+            codec,
+            memory: 64 << 20,
+            fan_in: 16,
+            temp_dir: env::temp_dir(),
+        }
+    }
+
+    pub fn sort<R, W>(&self, input: R, output: W) -> io::Result<()>
+    where
+        R: Read,
+        W: Write,
+        C::Record: Ord,
+        S: Sorter<C::Record>,
+    {
+        self.sort_by(input, output, C::Record::cmp)
+    }
+
+    /// Sorts the records in `input` according to `compare` and writes them
+    /// to `output`. On error whatever was written to `output` so far is
+    /// left there, but the temporary files are always removed.
+    pub fn sort_by<R, W, F>(&self, input: R, output: W, mut compare: F) -> io::Result<()>
+    where
+        R: Read,
+        W: Write,
+        F: FnMut(&C::Record, &C::Record) -> Ordering,
+        S: Sorter<C::Record>,
+    {
+        if self.fan_in < 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot merge fewer than 2 runs at once",
+            ));
+        }
+
+        let mut input = BufReader::new(input);
+        let mut output = BufWriter::new(output);
+        let mut runs = Vec::new();
+        let mut chunk = Vec::new();
+        let mut used = 0;
+        while let Some(record) = self.codec.decode(&mut input)? {
+            let size = self.codec.memory(&record);
+            if !chunk.is_empty() && used + size > self.memory {
+                runs.push(self.spill(&mut chunk, &mut compare)?);
+                used = 0;
+            }
+            used += size;
+            chunk.push(record);
+        }
+
+        if runs.is_empty() {
+            // everything fit in memory, so there's nothing to merge
+            self.sorter.sort_by_dyn(&mut chunk, &mut compare);
+            for record in &chunk {
+                self.codec.encode(record, &mut output)?;
+            }
+            return output.flush();
+        }
+        if !chunk.is_empty() {
+            runs.push(self.spill(&mut chunk, &mut compare)?);
+        }
+        drop(chunk);
+
+        // merge neighbouring runs so the runs stay in input order, which
+        // keeps the merges stable
+        while runs.len() > self.fan_in {
+            let mut merged = Vec::with_capacity(runs.len().div_ceil(self.fan_in));
+            let mut rest = runs.drain(..);
+            loop {
+                let group: Vec<_> = rest.by_ref().take(self.fan_in).collect();
+                match group.len() {
+                    0 => break,
+                    1 => merged.extend(group),
+                    _ => {
+                        let (run, file) = TempRun::create(&self.temp_dir)?;
+                        let mut file = BufWriter::new(file);
+                        self.merge(&group, &mut file, &mut compare)?;
+                        file.flush()?;
+                        merged.push(run);
+                    }
+                }
+            }
+            drop(rest);
+            runs = merged;
+        }
+
+        self.merge(&runs, &mut output, &mut compare)?;
+        output.flush()
+    }
+
+    /// Sorts `chunk` and writes it out to a new run, leaving `chunk` empty.
+    fn spill<F>(&self, chunk: &mut Vec<C::Record>, compare: &mut F) -> io::Result<TempRun>
+    where
+        F: FnMut(&C::Record, &C::Record) -> Ordering,
+        S: Sorter<C::Record>,
+    {
+        self.sorter.sort_by_dyn(chunk, compare);
+        let (run, file) = TempRun::create(&self.temp_dir)?;
+        let mut file = BufWriter::new(file);
+        for record in chunk.drain(..) {
+            self.codec.encode(&record, &mut file)?;
+        }
+        file.flush()?;
+        Ok(run)
+    }
+
+    /// Merges the sorted `runs` into `output` with a heap holding the
+    /// smallest record left in each run.
+    fn merge<W, F>(&self, runs: &[TempRun], output: &mut W, compare: &mut F) -> io::Result<()>
+    where
+        W: Write,
+        F: FnMut(&C::Record, &C::Record) -> Ordering,
+    {
+        let mut readers = runs
+            .iter()
+            .map(|run| File::open(&run.path).map(BufReader::new))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut heap = Vec::with_capacity(readers.len());
+        for (i, reader) in readers.iter_mut().enumerate() {
+            if let Some(record) = self.codec.decode(reader)? {
+                heap.push((record, i));
+            }
+        }
+
+        // `build_heap` builds a max heap, so flip the order to get the smallest
+        // record on top, and the earliest run on ties
+        let mut order = |a: &(C::Record, usize), b: &(C::Record, usize)| {
+            compare(&b.0, &a.0).then(b.1.cmp(&a.1))
+        };
+        heap_sort::build_heap(&mut heap, &mut order, &Probe::OFF);
+
+        while let Some((record, run)) = heap.first_mut() {
+            self.codec.encode(record, output)?;
+            match self.codec.decode(&mut readers[*run])? {
+                Some(next) => *record = next,
+                None => {
+                    heap.swap_remove(0);
+                }
+            }
+            heap_sort::heapify(&mut heap, 0, &mut order);
+        }
+        Ok(())
+    }
+}
+
+/// A sorted run in a temporary file, which is removed when this is dropped.
+struct TempRun {
+    path: PathBuf,
+}
+
+impl TempRun {
+    fn create(dir: &std::path::Path) -> io::Result<(TempRun, File)> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        loop {
+            let n = NEXT.fetch_add(1, AtomicOrdering::Relaxed);
+            let path = dir.join(format!("chonky-run-{}-{n}", process::id()));
+            let mut options = OpenOptions::new();
+            options.write(true).create_new(true);
+            // the runs hold the data being sorted, keep them from other users
+            #[cfg(unix)]
+            options.mode(0o600);
+            match options.open(&path) {
+                Ok(file) => return Ok((TempRun { path }, file)),
+                // left over from an earlier process with the same id
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Drop for TempRun {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExternalSort, LengthPrefixed, Lines, TempRun};
+    use crate::sorting::{MergeSort, QuickSort};
+    use alloc::format;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use std::io::{self, Read};
+    use std::path::PathBuf;
+    use std::{env, fs, process};
+
+    /// A fresh directory for one test's runs, so it can check they're gone.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("chonky-test-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+        dir
+    }
+
+    fn is_empty(dir: &PathBuf) -> bool {
+        fs::read_dir(dir).unwrap().next().is_none()
+    }
+
+    #[test]
+    fn sorts_lines() {
+        let dir = temp_dir("lines");
+        let words: Vec<String> = (0..5000u64)
+            .map(|i| format!("{:x}", i * 2_654_435_761 % 1_000_003))
+            .collect();
+        let input = words.join("\n");
+        let mut expected = words.clone();
+        expected.sort();
+
+        for (memory, fan_in) in [(1 << 20, 2), (4096, 2), (4096, 3), (1000, 16)] {
+            let mut sorter = ExternalSort::new(QuickSort {}, Lines);
+            sorter.memory = memory;
+            sorter.fan_in = fan_in;
+            sorter.temp_dir = dir.clone();
+
+            let mut output = Vec::new();
+            sorter.sort(input.as_bytes(), &mut output).unwrap();
+            let output = String::from_utf8(output).unwrap();
+            assert_eq!(output.lines().collect::<Vec<_>>(), expected);
+            assert!(is_empty(&dir));
+        }
+        fs::remove_dir(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("private");
+        let (run, file) = TempRun::create(&dir).unwrap();
+        let mode = file.metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        drop((run, file));
+        fs::remove_dir(dir).unwrap();
+    }
+
+    #[test]
+    fn stable_with_a_stable_sorter() {
+        let dir = temp_dir("stable");
+        let records: Vec<Vec<u8>> = (0..3000u32)
+            .map(|i| {
+                let mut record = Vec::from([(i * 7 % 5) as u8]);
+                record.extend(i.to_le_bytes());
+                record
+            })
+            .collect();
+        let mut input = Vec::new();
+        for record in &records {
+            input.extend((record.len() as u64).to_le_bytes());
+            input.extend(record);
+        }
+        let mut expected = records.clone();
+        expected.sort_by_key(|r| r[0]);
+        let mut expected_output = Vec::new();
+        for record in &expected {
+            expected_output.extend((record.len() as u64).to_le_bytes());
+            expected_output.extend(record);
+        }
+
+        let mut sorter = ExternalSort::new(MergeSort {}, LengthPrefixed);
+        sorter.memory = 2000;
+        sorter.fan_in = 4;
+        sorter.temp_dir = dir.clone();
+        let mut output = Vec::new();
+        sorter
+            .sort_by(&input[..], &mut output, |a, b| a[0].cmp(&b[0]))
+            .unwrap();
+        assert_eq!(output, expected_output);
+        fs::remove_dir(dir).unwrap();
+    }
+
+    /// Fails after handing out `ok` bytes.
+    struct Failing<'a> {
+        data: &'a [u8],
+        ok: usize,
+    }
+
+    impl Read for Failing<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.ok == 0 {
+                return Err(io::Error::other("disk on fire"));
+            }
+            let n = buf.len().min(self.ok).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            self.ok -= n;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn removes_runs_on_error() {
+        let dir = temp_dir("error");
+        let input: String = (0..10_000).map(|i| format!("{}\n", i % 97)).collect();
+        let mut sorter = ExternalSort::new(QuickSort {}, Lines);
+        sorter.memory = 1000;
+        sorter.temp_dir = dir.clone();
+
+        let failing = Failing {
+            data: input.as_bytes(),
+            ok: input.len() / 2,
+        };
+        let err = sorter.sort(failing, Vec::new()).unwrap_err();
+        assert_eq!(err.to_string(), "disk on fire");
+        assert!(is_empty(&dir));
+
+        let truncated = [5, 0, 0, 0, 0, 0, 0, 0, b'a', b'b'];
+        let sorter = ExternalSort::new(QuickSort {}, LengthPrefixed);
+        let err = sorter.sort(&truncated[..], Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        fs::remove_dir(dir).unwrap();
+    }
+}
@@ -1,41 +1,225 @@
-use super::Sorter;
+use core::cmp::Ordering;
 
+use super::instrument::{Observer, Probe};
+use super::{Complexity, Properties, Sorter};
+
+/// Heap sort sifting elements down by swapping them with their larger child.
 pub struct HeapSort;
 
-fn heapify<T: Ord>(slice: &mut [T], root: usize) {
-    let mut largest = root;
-    let left = 2 * root + 1;
-    let right = 2 * root + 2;
+/// Heap sort using Floyd's bottom-up sift, which finds the path of larger
+/// children down to a leaf first and then climbs back up it, taking about
+/// half the comparisons on average.
+pub struct BottomUpHeapSort;
+
+/// Sifts the element at `root` down until it's no less than its children.
+pub(super) fn heapify<T, F>(slice: &mut [T], root: usize, compare: &mut F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    sift_down(slice, root, compare, &Probe::OFF);
+}
+
+pub(crate) fn sift_down<T, F, O>(
+    slice: &mut [T],
+    mut root: usize,
+    compare: &mut F,
+    probe: &Probe<T, O>,
+) where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    loop {
+        let mut largest = root;
+        let left = 2 * root + 1;
+        let right = 2 * root + 2;
+
+        if left < slice.len() && compare(&slice[largest], &slice[left]) == Ordering::Less {
+            largest = left;
+        }
+
+        if right < slice.len() && compare(&slice[largest], &slice[right]) == Ordering::Less {
+            largest = right;
+        }
 
-    if left < slice.len() && slice[largest] < slice[left] {
-        largest = left;
+        if largest == root {
+            return;
+        }
+        probe.swap(slice, root, largest);
+        root = largest;
     }
+}
 
-    if right < slice.len() && slice[largest] < slice[right] {
-        largest = right;
+/// Sifts the element at `node` up until it's no greater than its parent,
+/// and returns where it ends up.
+#[cfg(feature = "alloc")]
+pub(crate) fn sift_up<T, F, O>(
+    slice: &mut [T],
+    mut node: usize,
+    compare: &mut F,
+    probe: &Probe<T, O>,
+) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    while node > 0 {
+        let parent = (node - 1) / 2;
+        if compare(&slice[parent], &slice[node]) != Ordering::Less {
+            break;
+        }
+        probe.swap(slice, parent, node);
+        node = parent;
+    }
+    node
+}
+
+/// Floyd's sift: the element at `root` usually belongs near the bottom, so
+/// follow the larger children to a leaf with one comparison per level, and
+/// then go back up to where it belongs.
+fn sift_down_bottom_up<T, F, O>(slice: &mut [T], root: usize, compare: &mut F, probe: &Probe<T, O>)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    let len = slice.len();
+    let mut leaf = root;
+    while 2 * leaf + 2 < len {
+        let left = 2 * leaf + 1;
+        leaf = if compare(&slice[left], &slice[left + 1]) == Ordering::Less {
+            left + 1
+        } else {
+            left
+        };
+    }
+    if 2 * leaf + 1 < len {
+        leaf = 2 * leaf + 1;
     }
 
-    if largest != root {
-        slice.swap(root, largest);
-        heapify(slice, largest);
+    while leaf != root && compare(&slice[root], &slice[leaf]) != Ordering::Less {
+        leaf = (leaf - 1) / 2;
+    }
+    // shift everything on the path from `root` to `leaf` up a level, and
+    // put the root's element at `leaf`
+    while leaf != root {
+        probe.swap(slice, root, leaf);
+        leaf = (leaf - 1) / 2;
     }
 }
 
 pub fn sort<T>(slice: &mut [T])
 where
     T: Ord,
+{
+    sort_by(slice, T::cmp);
+}
+
+pub fn sort_by<T, F>(slice: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    heap_sort(slice, &mut compare, &Probe::OFF);
+}
+
+pub(super) fn heap_sort<T, F, O>(slice: &mut [T], compare: &mut F, probe: &Probe<T, O>)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    heap_sort_with(slice, compare, probe, sift_down);
+}
+
+pub fn sort_bottom_up<T>(slice: &mut [T])
+where
+    T: Ord,
+{
+    sort_bottom_up_by(slice, T::cmp);
+}
+
+pub fn sort_bottom_up_by<T, F>(slice: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    heap_sort_with(slice, &mut compare, &Probe::OFF, sift_down_bottom_up);
+}
+
+/// Turns `slice` into a max-heap.
+pub(crate) fn build_heap<T, F, O>(slice: &mut [T], compare: &mut F, probe: &Probe<T, O>)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    for i in (0..(slice.len() / 2)).rev() {
+        sift_down(slice, i, compare, probe);
+    }
+}
+
+/// Sorts a slice that's already a max-heap, by moving the largest element
+/// to the end until the heap is empty.
+pub(crate) fn sort_heap<T, F>(slice: &mut [T], compare: &mut F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    pop_all(slice, compare, &Probe::OFF, sift_down);
+}
+
+fn heap_sort_with<T, F, O>(
+    slice: &mut [T],
+    compare: &mut F,
+    probe: &Probe<T, O>,
+    sift: fn(&mut [T], usize, &mut F, &Probe<T, O>),
+) where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
 {
     for i in (0..(slice.len() / 2)).rev() {
-        heapify(slice, i);
+        sift(slice, i, compare, probe);
     }
+    pop_all(slice, compare, probe, sift);
+}
 
+fn pop_all<T, F, O>(
+    slice: &mut [T],
+    compare: &mut F,
+    probe: &Probe<T, O>,
+    sift: fn(&mut [T], usize, &mut F, &Probe<T, O>),
+) where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
     for i in (0..slice.len()).rev() {
-        slice.swap(0, i);
-        heapify(&mut slice[..i], 0);
+        probe.swap(slice, 0, i);
+        sift(&mut slice[..i], 0, compare, probe);
     }
 }
 
+const PROPERTIES: Properties = Properties {
+    stable: false,
+    in_place: true,
+    requires_clone: false,
+    best: Complexity::Linearithmic,
+    average: Complexity::Linearithmic,
+    worst: Complexity::Linearithmic,
+};
+
 impl<T> Sorter<T> for HeapSort {
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+        sort_by(slice, compare);
+    }
+
+    fn properties(&self) -> Properties {
+        PROPERTIES
+    }
+
+    fn sort_observed(
+        &self,
+        slice: &mut [T],
+        compare: &mut dyn FnMut(&T, &T) -> Ordering,
+        observer: &dyn Observer<T>,
+    ) {
+        let probe = Probe::new(slice, observer);
+        heap_sort(slice, &mut probe.observe(compare), &probe);
+    }
+
     fn sort(&self, slice: &mut [T])
     where
         T: Ord,
@@ -43,3 +227,35 @@ impl<T> Sorter<T> for HeapSort {
         sort(slice);
     }
 }
+
+impl<T> Sorter<T> for BottomUpHeapSort {
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+        sort_bottom_up_by(slice, compare);
+    }
+
+    fn properties(&self) -> Properties {
+        PROPERTIES
+    }
+
+    fn sort_observed(
+        &self,
+        slice: &mut [T],
+        compare: &mut dyn FnMut(&T, &T) -> Ordering,
+        observer: &dyn Observer<T>,
+    ) {
+        let probe = Probe::new(slice, observer);
+        heap_sort_with(
+            slice,
+            &mut probe.observe(compare),
+            &probe,
+            sift_down_bottom_up,
+        );
+    }
+
+    fn sort(&self, slice: &mut [T])
+    where
+        T: Ord,
+    {
+        sort_bottom_up(slice);
+    }
+}
@@ -0,0 +1,328 @@
+//! Deterministic inputs for testing and benchmarking sorters.
+//!
+//! Everything random takes a seed, so the same call always returns the same
+//! input. [`killer`] builds an input that makes a quicksort as slow as it
+//! can be, by watching the comparisons it makes.
+
+use alloc::vec::Vec;
+
+use super::Sorter;
+
+/// A small, fast pseudorandom number generator (SplitMix64). Not suitable
+/// for anything that needs to be unpredictable.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub const fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`, with a negligible bias for `n` much smaller than
+    /// 2^64.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is 0.
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "cannot pick a number below 0");
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+
+    /// A number in `0.0..1.0`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        for i in (1..slice.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            slice.swap(i, j);
+        }
+    }
+}
+
+/// The shapes of input [`Distribution::generate`] can produce, with the
+/// parameters the benchmarks use.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Distribution {
+    Random,
+    Sorted,
+    Reversed,
+    /// Eight ascending runs.
+    Sawtooth,
+    /// Eight distinct values.
+    FewUnique,
+    PipeOrgan,
+    /// Sorted, with a swap per hundred elements.
+    NearlySorted,
+}
+
+impl Distribution {
+    pub const ALL: [Distribution; 7] = [
+        Distribution::Random,
+        Distribution::Sorted,
+        Distribution::Reversed,
+        Distribution::Sawtooth,
+        Distribution::FewUnique,
+        Distribution::PipeOrgan,
+        Distribution::NearlySorted,
+    ];
+
+    pub fn generate(self, len: usize, seed: u64) -> Vec<u64> {
+        match self {
+            Distribution::Random => random(len, seed),
+            Distribution::Sorted => sorted(len),
+            Distribution::Reversed => reversed(len),
+            Distribution::Sawtooth => sawtooth(len, len.div_ceil(8)),
+            Distribution::FewUnique => few_unique(len, 8, seed),
+            Distribution::PipeOrgan => pipe_organ(len),
+            Distribution::NearlySorted => nearly_sorted(len, len / 100, seed),
+        }
+    }
+}
+
+pub fn random(len: usize, seed: u64) -> Vec<u64> {
+    let mut rng = Rng::new(seed);
+    (0..len).map(|_| rng.next_u64()).collect()
+}
+
+pub fn sorted(len: usize) -> Vec<u64> {
+    (0..len as u64).collect()
+}
+
+pub fn reversed(len: usize) -> Vec<u64> {
+    (0..len as u64).rev().collect()
+}
+
+/// Ascending runs of `period` elements, `0, 1, .., period - 1, 0, 1, ..`.
+pub fn sawtooth(len: usize, period: usize) -> Vec<u64> {
+    let period = period.max(1) as u64;
+    (0..len as u64).map(|i| i % period).collect()
+}
+
+/// Ascending up to the middle, then descending.
+pub fn pipe_organ(len: usize) -> Vec<u64> {
+    (0..len).map(|i| i.min(len - 1 - i) as u64).collect()
+}
+
+/// Values drawn uniformly from `0..unique`.
+pub fn few_unique(len: usize, unique: u64, seed: u64) -> Vec<u64> {
+    let mut rng = Rng::new(seed);
+    (0..len).map(|_| rng.below(unique.max(1))).collect()
+}
+
+/// `0..len` in order, with `swaps` random pairs of elements swapped.
+pub fn nearly_sorted(len: usize, swaps: usize, seed: u64) -> Vec<u64> {
+    let mut list = sorted(len);
+    if len > 0 {
+        let mut rng = Rng::new(seed);
+        for _ in 0..swaps {
+            let i = rng.below(len as u64) as usize;
+            let j = rng.below(len as u64) as usize;
+            list.swap(i, j);
+        }
+    }
+    list
+}
+
+/// Values in `1..=n` drawn from a Zipf distribution with exponent `s`, so
+/// `k` comes up in proportion to `1 / k^s`.
+#[cfg(feature = "std")]
+pub fn zipf(len: usize, n: u64, s: f64, seed: u64) -> Vec<u64> {
+    let mut total = 0.0;
+    let cumulative: Vec<f64> = (1..=n.max(1))
+        .map(|k| {
+            total += (k as f64).powf(-s);
+            total
+        })
+        .collect();
+
+    let mut rng = Rng::new(seed);
+    (0..len)
+        .map(|_| {
+            let x = rng.next_f64() * total;
+            let i = cumulative.partition_point(|c| *c <= x);
+            i.min(cumulative.len() - 1) as u64 + 1
+        })
+        .collect()
+}
+
+/// Builds an input of `len` elements that `sorter` sorts as slowly as it
+/// can, using McIlroy's adversary.
+///
+/// The adversary sorts the positions `0..len`, deciding what each value is
+/// only when the sorter compares it. Values start out as "gas", greater
+/// than every decided value, and when two gas values are compared one of
+/// them is frozen as the next smallest value. The one frozen is the one
+/// that isn't the likely pivot, so the pivot stays as large as possible and
+/// partitions come out as lopsided as possible. Since the answers are
+/// consistent with the values it returns, sorting them with `sorter` makes
+/// exactly the same comparisons.
+///
+/// `sorter` has to only look at the elements through the comparator, and
+/// make the same choices every time it sorts the same slice.
+///
+/// ```
+/// use chonky_structures::sorting::{inputs, instrument::Instrumented, HeapSort};
+///
+/// let mut list = inputs::killer(&HeapSort, 1000);
+/// let stats = Instrumented::new(HeapSort).sort(&mut list);
+/// assert!(stats.comparisons < 2 * 1000 * 10);
+/// ```
+pub fn killer<S>(sorter: &S, len: usize) -> Vec<u64>
+where
+    S: Sorter<u64> + ?Sized,
+{
+    let gas = len as u64;
+    let mut values = alloc::vec![gas; len];
+    let mut solid = 0;
+    let mut candidate = 0;
+
+    let mut positions = sorted(len);
+    sorter.sort_by_dyn(&mut positions, &mut |x, y| {
+        let (x, y) = (*x as usize, *y as usize);
+        if values[x] == gas && values[y] == gas {
+            let frozen = if x == candidate { x } else { y };
+            values[frozen] = solid;
+            solid += 1;
+        }
+        if values[x] == gas {
+            candidate = x;
+        } else if values[y] == gas {
+            candidate = y;
+        }
+        values[x].cmp(&values[y])
+    });
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorting::instrument::Instrumented;
+    use crate::sorting::{quick_sort, Properties, QuickSort};
+    use core::cmp::Ordering;
+
+    #[test]
+    fn generators() {
+        assert_eq!(sawtooth(7, 3), [0, 1, 2, 0, 1, 2, 0]);
+        assert_eq!(pipe_organ(6), [0, 1, 2, 2, 1, 0]);
+        assert_eq!(pipe_organ(5), [0, 1, 2, 1, 0]);
+        assert_eq!(random(100, 7), random(100, 7));
+        assert_ne!(random(100, 7), random(100, 8));
+
+        assert!(few_unique(1000, 5, 1).iter().all(|x| *x < 5));
+        let misplaced = nearly_sorted(1000, 10, 1)
+            .iter()
+            .enumerate()
+            .filter(|(i, x)| *i as u64 != **x)
+            .count();
+        assert!(0 < misplaced && misplaced <= 20);
+
+        let mut shuffled = sorted(100);
+        Rng::new(3).shuffle(&mut shuffled);
+        assert_ne!(shuffled, sorted(100));
+        shuffled.sort();
+        assert_eq!(shuffled, sorted(100));
+
+        for distribution in Distribution::ALL {
+            assert_eq!(distribution.generate(123, 0).len(), 123);
+            assert!(distribution.generate(0, 0).is_empty());
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn zipf_favours_small_values() {
+        let list = zipf(10_000, 100, 1.0, 5);
+        let count = |k| list.iter().filter(|x| **x == k).count();
+        assert!(list.iter().all(|x| (1..=100).contains(x)));
+        // about 1/H(100) ~ 19% ones, and half as many twos
+        assert!((1700..2200).contains(&count(1)));
+        assert!((800..1150).contains(&count(2)));
+        assert!(count(1) > 10 * count(50));
+    }
+
+    /// Quicksort taking the median of the first, middle and last elements
+    /// as the pivot, with no fallback.
+    struct NaiveQuickSort;
+
+    fn naive_quick_sort(slice: &mut [u64], compare: &mut dyn FnMut(&u64, &u64) -> Ordering) {
+        if slice.len() <= 1 {
+            return;
+        }
+        let (mut a, mut b, mut c) = (0, slice.len() / 2, slice.len() - 1);
+        if compare(&slice[b], &slice[a]) == Ordering::Less {
+            core::mem::swap(&mut a, &mut b);
+        }
+        if compare(&slice[c], &slice[b]) == Ordering::Less {
+            core::mem::swap(&mut b, &mut c);
+            if compare(&slice[b], &slice[a]) == Ordering::Less {
+                core::mem::swap(&mut a, &mut b);
+            }
+        }
+        slice.swap(0, b);
+
+        let mut mid = 0;
+        for i in 1..slice.len() {
+            if compare(&slice[i], &slice[0]) == Ordering::Less {
+                mid += 1;
+                slice.swap(mid, i);
+            }
+        }
+        slice.swap(0, mid);
+        naive_quick_sort(&mut slice[..mid], compare);
+        naive_quick_sort(&mut slice[mid + 1..], compare);
+    }
+
+    impl Sorter<u64> for NaiveQuickSort {
+        fn sort_by_dyn(&self, slice: &mut [u64], compare: &mut dyn FnMut(&u64, &u64) -> Ordering) {
+            naive_quick_sort(slice, compare);
+        }
+
+        fn properties(&self) -> Properties {
+            quick_sort::PROPERTIES
+        }
+    }
+
+    #[test]
+    fn killer_is_quadratic_without_a_fallback() {
+        let n = 2000;
+        let mut list = killer(&NaiveQuickSort, n);
+        let stats = Instrumented::new(NaiveQuickSort).sort(&mut list);
+        assert!(list.windows(2).all(|w| w[0] <= w[1]));
+        assert!(stats.comparisons > n * n / 8);
+
+        let mut list = random(n, 1);
+        let stats = Instrumented::new(NaiveQuickSort).sort(&mut list);
+        assert!(stats.comparisons < 4 * n * 11);
+    }
+
+    // `QuickSort` falls back to heap sort after too many badly balanced
+    // partitions, which keeps it O(n log n) against the adversary.
+    #[test]
+    fn quick_sort_survives_the_killer() {
+        for n in [100, 1000, 10_000] {
+            let mut list = killer(&QuickSort {}, n);
+            let stats = Instrumented::new(QuickSort {}).sort(&mut list);
+            assert!(list.windows(2).all(|w| w[0] <= w[1]));
+            let log = (usize::BITS - n.leading_zeros()) as usize;
+            assert!(
+                stats.comparisons < 6 * n * log,
+                "{} comparisons for {n} elements",
+                stats.comparisons
+            );
+        }
+    }
+}
@@ -1,21 +1,64 @@
-use super::Sorter;
+use core::cmp::Ordering;
+
+use super::instrument::{Observer, Probe};
+use super::{Complexity, Properties, Sorter};
 
 pub struct InsertionSort;
 
 pub fn sort<T>(slice: &mut [T])
 where
     T: Ord,
+{
+    sort_by(slice, T::cmp);
+}
+
+pub fn sort_by<T, F>(slice: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    insertion_sort(slice, &mut compare, &Probe::OFF);
+}
+
+pub(super) fn insertion_sort<T, F, O>(slice: &mut [T], compare: &mut F, probe: &Probe<T, O>)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
 {
     for i in 0..slice.len() {
         let mut j = i;
-        while j != 0 && slice[j] < slice[j - 1] {
-            slice.swap(j, j - 1);
+        while j != 0 && compare(&slice[j], &slice[j - 1]) == Ordering::Less {
+            probe.swap(slice, j, j - 1);
             j -= 1;
         }
     }
 }
 
 impl<T> Sorter<T> for InsertionSort {
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+        sort_by(slice, compare);
+    }
+
+    fn properties(&self) -> Properties {
+        Properties {
+            stable: true,
+            in_place: true,
+            requires_clone: false,
+            best: Complexity::Linear,
+            average: Complexity::Quadratic,
+            worst: Complexity::Quadratic,
+        }
+    }
+
+    fn sort_observed(
+        &self,
+        slice: &mut [T],
+        compare: &mut dyn FnMut(&T, &T) -> Ordering,
+        observer: &dyn Observer<T>,
+    ) {
+        let probe = Probe::new(slice, observer);
+        insertion_sort(slice, &mut probe.observe(compare), &probe);
+    }
+
     fn sort(&self, slice: &mut [T])
     where
         T: Ord,
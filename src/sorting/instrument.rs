@@ -0,0 +1,445 @@
+//! Watching what a sorter does.
+//!
+//! Every [`Sorter`] can report the comparisons it makes to an [`Observer`]
+//! through [`Sorter::sort_observed`]. The simple sorters along with
+//! [`QuickSort`](super::QuickSort), [`MergeSort`](super::MergeSort),
+//! [`BottomUpMergeSort`](super::BottomUpMergeSort),
+//! [`BlockMergeSort`](super::BlockMergeSort) and the heap sorts also report
+//! every element they move, and the ranges they recurse into.
+//!
+//! [`Instrumented`] wraps a sorter to count all of that up into
+//! [`SortStats`].
+
+use core::cell::Cell;
+use core::cmp::Ordering;
+use core::mem;
+use core::ops::Range;
+use core::ptr;
+
+use super::Sorter;
+
+/// Receives the steps a sorter takes while sorting a slice of `T`. Indices
+/// are positions in that slice.
+///
+/// The methods take `&self` since the comparator and the sorter both report
+/// to the observer, use a [`Cell`] or similar to keep track of anything.
+pub trait Observer<T: ?Sized> {
+    /// Two elements were compared. An element that's been moved out of the
+    /// slice, into a merge buffer say, has no index.
+    fn compare(&self, a: Option<usize>, b: Option<usize>) {
+        let _ = (a, b);
+    }
+
+    /// The elements at `i` and `j` were swapped.
+    fn swap(&self, i: usize, j: usize) {
+        let _ = (i, j);
+    }
+
+    /// `value` was moved into `i` from outside of the slice, or from a
+    /// position that is now a gap.
+    fn write(&self, i: usize, value: &T) {
+        let _ = (i, value);
+    }
+
+    /// The sorter started working on `range` on its own, by recursing into
+    /// it for example.
+    fn enter(&self, range: Range<usize>) {
+        let _ = range;
+    }
+
+    /// The sorter is done with a range it entered.
+    fn leave(&self, range: Range<usize>) {
+        let _ = range;
+    }
+}
+
+/// An observer that ignores everything, which sorting without an observer
+/// uses so the reporting compiles down to nothing.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct NoOp;
+
+impl<T: ?Sized> Observer<T> for NoOp {}
+
+/// Turns the elements and subslices an algorithm works with back into
+/// positions in the slice being sorted, and reports them to the observer.
+pub(crate) struct Probe<'a, T, O: ?Sized> {
+    start: *const T,
+    len: usize,
+    /// Whether swaps are reported by the positions they're given, see
+    /// [`Probe::by_position`].
+    by_position: bool,
+    observer: &'a O,
+}
+
+impl<T> Probe<'static, T, NoOp> {
+    pub(crate) const OFF: Self = Probe {
+        start: ptr::null(),
+        len: 0,
+        by_position: false,
+        observer: &NoOp,
+    };
+}
+
+impl<'a, T, O> Probe<'a, T, O>
+where
+    O: Observer<T> + ?Sized,
+{
+    pub(crate) fn new(slice: &[T], observer: &'a O) -> Self {
+        Probe {
+            start: slice.as_ptr(),
+            len: slice.len(),
+            by_position: false,
+            observer,
+        }
+    }
+
+    /// A probe for an algorithm that only swaps, and only in slices that
+    /// start where the observed slice does, so the positions it swaps are
+    /// already positions in the observed slice. Unlike finding positions
+    /// from addresses, this works for zero sized elements.
+    #[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+    pub(crate) fn by_position(observer: &'a O) -> Self {
+        Probe {
+            start: ptr::null(),
+            len: 0,
+            by_position: true,
+            observer,
+        }
+    }
+
+    /// The position of the element at `x`, if it's in the slice.
+    fn index(&self, x: *const T) -> Option<usize> {
+        let size = mem::size_of::<T>();
+        if size == 0 {
+            return None;
+        }
+        let i = (x as usize).wrapping_sub(self.start as usize) / size;
+        (i < self.len).then_some(i)
+    }
+
+    fn range(&self, slice: &[T]) -> Option<Range<usize>> {
+        let start = match slice.first() {
+            Some(first) => self.index(first)?,
+            None => return None,
+        };
+        Some(start..start + slice.len())
+    }
+
+    pub(crate) fn compare(&self, a: &T, b: &T) {
+        self.observer.compare(self.index(a), self.index(b));
+    }
+
+    /// Wraps `compare` to report every comparison.
+    pub(crate) fn observe<'p, F>(&'p self, mut compare: F) -> impl FnMut(&T, &T) -> Ordering + 'p
+    where
+        F: FnMut(&T, &T) -> Ordering + 'p,
+    {
+        move |a, b| {
+            self.compare(a, b);
+            compare(a, b)
+        }
+    }
+
+    /// Swaps `slice[i]` and `slice[j]` and reports it.
+    pub(crate) fn swap(&self, slice: &mut [T], i: usize, j: usize) {
+        if self.by_position {
+            self.observer.swap(i, j);
+        } else if let (Some(a), Some(b)) = (self.index(&slice[i]), self.index(&slice[j])) {
+            self.observer.swap(a, b);
+        }
+        slice.swap(i, j);
+    }
+
+    /// Reverses `slice`, reporting it as swaps.
+    pub(crate) fn reverse(&self, slice: &mut [T]) {
+        let len = slice.len();
+        for i in 0..len / 2 {
+            if let (Some(a), Some(b)) = (self.index(&slice[i]), self.index(&slice[len - 1 - i])) {
+                self.observer.swap(a, b);
+            }
+        }
+        slice.reverse();
+    }
+
+    /// Reports that `values` were moved to `dest` and on.
+    #[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+    pub(crate) fn write(&self, dest: *const T, values: &[T]) {
+        if let Some(i) = self.index(dest) {
+            for (i, value) in (i..self.len).zip(values) {
+                self.observer.write(i, value);
+            }
+        }
+    }
+
+    /// Reports entering `slice`, and returns what to pass to
+    /// [`Probe::leave`] when done with it.
+    pub(crate) fn enter(&self, slice: &[T]) -> Option<Range<usize>> {
+        let range = self.range(slice)?;
+        self.observer.enter(range.clone());
+        Some(range)
+    }
+
+    pub(crate) fn leave(&self, entered: Option<Range<usize>>) {
+        if let Some(range) = entered {
+            self.observer.leave(range);
+        }
+    }
+}
+
+/// How much work a sort did.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub struct SortStats {
+    pub comparisons: usize,
+    pub swaps: usize,
+    /// Elements moved into the slice other than by swapping.
+    pub writes: usize,
+    /// How deep the sorter recursed, 0 if it didn't report entering any
+    /// ranges.
+    pub max_depth: usize,
+    /// Allocations made by the sorting thread, only counted when
+    /// [`CountingAllocator`] is the global allocator.
+    #[cfg(feature = "std")]
+    pub allocations: usize,
+    /// Bytes allocated by the sorting thread, see `allocations`.
+    #[cfg(feature = "std")]
+    pub allocated_bytes: usize,
+}
+
+#[derive(Default)]
+struct Counter {
+    comparisons: Cell<usize>,
+    swaps: Cell<usize>,
+    writes: Cell<usize>,
+    depth: Cell<usize>,
+    max_depth: Cell<usize>,
+}
+
+fn increment(cell: &Cell<usize>) {
+    cell.set(cell.get() + 1);
+}
+
+impl<T: ?Sized> Observer<T> for Counter {
+    fn compare(&self, _: Option<usize>, _: Option<usize>) {
+        increment(&self.comparisons);
+    }
+
+    fn swap(&self, _: usize, _: usize) {
+        increment(&self.swaps);
+    }
+
+    fn write(&self, _: usize, _: &T) {
+        increment(&self.writes);
+    }
+
+    fn enter(&self, _: Range<usize>) {
+        increment(&self.depth);
+        self.max_depth
+            .set(self.max_depth.get().max(self.depth.get()));
+    }
+
+    fn leave(&self, _: Range<usize>) {
+        self.depth.set(self.depth.get() - 1);
+    }
+}
+
+/// Wraps a sorter to measure how much work it does.
+///
+/// ```
+/// use chonky_structures::sorting::{instrument::Instrumented, InsertionSort};
+///
+/// let mut list = [5, 4, 3, 2, 1];
+/// let stats = Instrumented::new(InsertionSort).sort(&mut list);
+/// assert_eq!(list, [1, 2, 3, 4, 5]);
+/// assert_eq!(stats.swaps, 10);
+/// ```
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Instrumented<S> {
+    pub inner: S,
+}
+
+impl<S> Instrumented<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+
+    pub fn sort<T>(&self, slice: &mut [T]) -> SortStats
+    where
+        T: Ord,
+        S: Sorter<T>,
+    {
+        self.sort_by(slice, T::cmp)
+    }
+
+    pub fn sort_by<T, F>(&self, slice: &mut [T], mut compare: F) -> SortStats
+    where
+        F: FnMut(&T, &T) -> Ordering,
+        S: Sorter<T>,
+    {
+        let counter = Counter::default();
+        #[cfg(feature = "std")]
+        let before = allocated();
+        self.inner.sort_observed(slice, &mut compare, &counter);
+        #[cfg(feature = "std")]
+        let after = allocated();
+
+        SortStats {
+            comparisons: counter.comparisons.get(),
+            swaps: counter.swaps.get(),
+            writes: counter.writes.get(),
+            max_depth: counter.max_depth.get(),
+            #[cfg(feature = "std")]
+            allocations: after.0 - before.0,
+            #[cfg(feature = "std")]
+            allocated_bytes: after.1 - before.1,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+use counting_allocator::allocated;
+#[cfg(feature = "std")]
+pub use counting_allocator::CountingAllocator;
+
+#[cfg(feature = "std")]
+mod counting_allocator {
+    use core::alloc::{GlobalAlloc, Layout};
+    use core::cell::Cell;
+    use std::alloc::System;
+
+    std::thread_local! {
+        static ALLOCATED: Cell<(usize, usize)> = const { Cell::new((0, 0)) };
+    }
+
+    /// The number of allocations and bytes allocated by this thread so far.
+    pub(super) fn allocated() -> (usize, usize) {
+        ALLOCATED.try_with(Cell::get).unwrap_or((0, 0))
+    }
+
+    /// A global allocator that counts the allocations each thread makes, so
+    /// [`Instrumented`](super::Instrumented) can report them.
+    ///
+    /// ```
+    /// use chonky_structures::sorting::instrument::CountingAllocator;
+    /// use std::alloc::System;
+    ///
+    /// #[global_allocator]
+    /// static ALLOCATOR: CountingAllocator = CountingAllocator::new(System);
+    /// # fn main() {}
+    /// ```
+    pub struct CountingAllocator<A = System> {
+        inner: A,
+    }
+
+    impl<A> CountingAllocator<A> {
+        pub const fn new(inner: A) -> Self {
+            Self { inner }
+        }
+    }
+
+    unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            // the thread local can be gone while the thread shuts down,
+            // those allocations just aren't counted
+            let _ = ALLOCATED.try_with(|a| {
+                let (count, bytes) = a.get();
+                a.set((count + 1, bytes + layout.size()));
+            });
+            self.inner.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            self.inner.dealloc(ptr, layout);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::Instrumented;
+    use crate::sorting::*;
+    use alloc::vec::Vec;
+
+    fn random(n: u32) -> Vec<u32> {
+        (0..n)
+            .map(|i| i.wrapping_mul(2_654_435_761) % 1_000_003)
+            .collect()
+    }
+
+    #[test]
+    fn counts_moves() {
+        let reversed: Vec<u32> = (0..100).rev().collect();
+        let measure = |sorter: &dyn Sorter<u32>| {
+            let mut list = reversed.clone();
+            Instrumented::new(sorter).sort(&mut list)
+        };
+
+        let stats = measure(&InsertionSort);
+        assert_eq!((stats.comparisons, stats.swaps), (4950, 4950));
+        assert_eq!(measure(&BubbleSort).swaps, 4950);
+        assert!(measure(&SelectionSort).swaps <= 99);
+
+        let stats = measure(&HeapSort);
+        assert!(stats.swaps > 0 && stats.writes == 0 && stats.max_depth == 0);
+
+        let stats = Instrumented::new(QuickSort {}).sort(&mut random(10_000));
+        assert!(stats.swaps > 0 && stats.max_depth > 1);
+        assert!(stats.max_depth <= 2 * 14, "depth {}", stats.max_depth);
+
+        let stats = Instrumented::new(MergeSort {}).sort(&mut random(10_000));
+        // halving 10000 gets below `MIN_RUN` after 10 levels
+        assert!(stats.writes > 10_000 && stats.max_depth == 11);
+
+        let stats = Instrumented::new(BottomUpMergeSort {}).sort(&mut random(10_000));
+        assert!(stats.writes > 10_000);
+    }
+
+    #[test]
+    fn heap_sort_comparisons() {
+        let comparisons = |sorter: &dyn Sorter<u32>, mut list: Vec<u32>| {
+            let stats = Instrumented::new(sorter).sort(&mut list);
+            assert!(list.windows(2).all(|w| w[0] <= w[1]));
+            stats.comparisons
+        };
+
+        // n log n is about 133000, and the variants get closer to it
+        let heap = comparisons(&HeapSort, random(10_000));
+        let bottom_up = comparisons(&BottomUpHeapSort, random(10_000));
+        let weak = comparisons(&WeakHeapSort, random(10_000));
+        assert!(
+            weak < bottom_up && bottom_up < heap,
+            "{weak} {bottom_up} {heap}"
+        );
+        assert!(weak < 10_000 * 14);
+
+        let sorted: Vec<u32> = (0..10_000).collect();
+        let smooth = comparisons(&SmoothSort, sorted.clone());
+        assert!(smooth < 5 * 10_000, "{smooth}");
+        assert!(comparisons(&HeapSort, sorted) > 10_000 * 10);
+        assert!(comparisons(&SmoothSort, random(10_000)) < 2 * heap);
+    }
+
+    /// Checks that doubling the input multiplies the number of comparisons
+    /// by no more than the sorter's average complexity allows.
+    #[test]
+    fn comparisons_grow_as_advertised() {
+        for entry in registry::entries::<u32>() {
+            let (name, sorter) = (entry.name, entry.build());
+            let comparisons = |n| {
+                let mut list = random(n);
+                let stats = Instrumented::new(&*sorter).sort(&mut list);
+                assert!(list.windows(2).all(|w| w[0] <= w[1]), "{name}");
+                stats.comparisons as f64
+            };
+            let ratio = comparisons(4000) / comparisons(2000);
+            let limit = match entry.properties.average {
+                Complexity::Linear => 2.0,
+                Complexity::Linearithmic => 2.2,
+                Complexity::LinearithmicSquared => 2.4,
+                Complexity::FourThirds => 2.52,
+                Complexity::ThreeHalves => 2.83,
+                Complexity::Quadratic => 4.0,
+            };
+            assert!(ratio <= limit * 1.1, "{name}: ratio {ratio}");
+        }
+    }
+}
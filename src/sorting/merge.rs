@@ -0,0 +1,505 @@
+//! Merging sorted sequences.
+//!
+//! Lazy iterators that merge two ([`merge_by`]) or any number
+//! ([`kmerge_by`]) of sorted iterators, or drop the duplicates from one
+//! ([`dedup_sorted`]), checks for sortedness that work on slices and
+//! iterators alike, and [`merge_in_place`] for two adjacent runs of a slice.
+//! The merge sorts share the buffered merge of two runs here too.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::iter::Peekable;
+#[cfg(feature = "alloc")]
+use core::mem::MaybeUninit;
+#[cfg(feature = "alloc")]
+use core::{mem, ptr, slice};
+
+use super::block_merge_sort::sym_merge;
+#[cfg(feature = "alloc")]
+use super::heap_sort;
+#[cfg(feature = "alloc")]
+use super::instrument::Observer;
+use super::instrument::Probe;
+
+/// The comparator the functions that don't take one use.
+type Cmp<T> = fn(&T, &T) -> Ordering;
+/// The equality [`dedup_sorted`] uses.
+type Same<T> = fn(&T, &T) -> bool;
+
+/// Merges two sorted iterators into one sorted iterator, created by
+/// [`merge`] and [`merge_by`].
+pub struct MergeBy<A, B, F>
+where
+    A: Iterator,
+    B: Iterator<Item = A::Item>,
+{
+    a: Peekable<A>,
+    b: Peekable<B>,
+    compare: F,
+}
+
+/// Merges two sorted iterators. Elements of `a` come before equal elements
+/// of `b`.
+///
+/// ```
+/// use chonky_structures::sorting::merge::merge;
+///
+/// let merged: Vec<_> = merge([1, 3, 5], [2, 3, 4]).collect();
+/// assert_eq!(merged, [1, 2, 3, 3, 4, 5]);
+/// ```
+pub fn merge<A, B>(a: A, b: B) -> MergeBy<A::IntoIter, B::IntoIter, Cmp<A::Item>>
+where
+    A: IntoIterator,
+    B: IntoIterator<Item = A::Item>,
+    A::Item: Ord,
+{
+    merge_by(a, b, Ord::cmp)
+}
+
+pub fn merge_by<A, B, F>(a: A, b: B, compare: F) -> MergeBy<A::IntoIter, B::IntoIter, F>
+where
+    A: IntoIterator,
+    B: IntoIterator<Item = A::Item>,
+    F: FnMut(&A::Item, &A::Item) -> Ordering,
+{
+    MergeBy {
+        a: a.into_iter().peekable(),
+        b: b.into_iter().peekable(),
+        compare,
+    }
+}
+
+impl<A, B, F> Iterator for MergeBy<A, B, F>
+where
+    A: Iterator,
+    B: Iterator<Item = A::Item>,
+    F: FnMut(&A::Item, &A::Item) -> Ordering,
+{
+    type Item = A::Item;
+
+    fn next(&mut self) -> Option<A::Item> {
+        match (self.a.peek(), self.b.peek()) {
+            (Some(a), Some(b)) if (self.compare)(b, a) == Ordering::Less => self.b.next(),
+            (Some(_), _) => self.a.next(),
+            (None, _) => self.b.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (a_low, a_high) = self.a.size_hint();
+        let (b_low, b_high) = self.b.size_hint();
+        let high = match (a_high, b_high) {
+            (Some(a), Some(b)) => a.checked_add(b),
+            _ => None,
+        };
+        (a_low.saturating_add(b_low), high)
+    }
+}
+
+/// Merges any number of sorted iterators into one sorted iterator, created
+/// by [`kmerge`] and [`kmerge_by`].
+///
+/// Keeps a heap of the next element of each iterator, so each element takes
+/// O(log k) comparisons for `k` iterators.
+#[cfg(feature = "alloc")]
+pub struct KMerge<I, F>
+where
+    I: Iterator,
+{
+    /// The next element of each iterator that isn't done, with the
+    /// iterator's position among the inputs to break ties.
+    heap: Vec<(I::Item, usize, I)>,
+    compare: F,
+}
+
+/// Merges sorted iterators. Equal elements come out in the order of the
+/// iterators they came from.
+///
+/// ```
+/// use chonky_structures::sorting::merge::kmerge;
+///
+/// let merged: Vec<_> = kmerge([vec![1, 4, 7], vec![2, 5], vec![3, 6, 9]]).collect();
+/// assert_eq!(merged, [1, 2, 3, 4, 5, 6, 7, 9]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn kmerge<I>(
+    iters: I,
+) -> KMerge<<I::Item as IntoIterator>::IntoIter, Cmp<<I::Item as IntoIterator>::Item>>
+where
+    I: IntoIterator,
+    I::Item: IntoIterator,
+    <I::Item as IntoIterator>::Item: Ord,
+{
+    kmerge_by(iters, Ord::cmp)
+}
+
+#[cfg(feature = "alloc")]
+pub fn kmerge_by<I, F>(iters: I, mut compare: F) -> KMerge<<I::Item as IntoIterator>::IntoIter, F>
+where
+    I: IntoIterator,
+    I::Item: IntoIterator,
+    F: FnMut(&<I::Item as IntoIterator>::Item, &<I::Item as IntoIterator>::Item) -> Ordering,
+{
+    let mut heap: Vec<_> = iters
+        .into_iter()
+        .map(IntoIterator::into_iter)
+        .enumerate()
+        .filter_map(|(i, mut iter)| Some((iter.next()?, i, iter)))
+        .collect();
+    let mut order = |a: &_, b: &_| kmerge_order(&mut compare, a, b);
+    heap_sort::build_heap(&mut heap, &mut order, &Probe::OFF);
+    KMerge { heap, compare }
+}
+
+/// `build_heap` builds a max heap, so flip the order to get the smallest
+/// element on top, and the earliest iterator on ties.
+#[cfg(feature = "alloc")]
+fn kmerge_order<T, I, F>(compare: &mut F, a: &(T, usize, I), b: &(T, usize, I)) -> Ordering
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    compare(&b.0, &a.0).then(b.1.cmp(&a.1))
+}
+
+#[cfg(feature = "alloc")]
+impl<I, F> Iterator for KMerge<I, F>
+where
+    I: Iterator,
+    F: FnMut(&I::Item, &I::Item) -> Ordering,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let (head, _, iter) = self.heap.first_mut()?;
+        let item = match iter.next() {
+            Some(next) => mem::replace(head, next),
+            None => self.heap.swap_remove(0).0,
+        };
+        let compare = &mut self.compare;
+        heap_sort::heapify(&mut self.heap, 0, &mut |a, b| kmerge_order(compare, a, b));
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.heap.iter().map(|(_, _, iter)| iter.size_hint()).fold(
+            (0, Some(0)),
+            |(low, high), (l, h)| {
+                let high = match (high, h) {
+                    (Some(high), Some(h)) => high.checked_add(h).and_then(|x| x.checked_add(1)),
+                    _ => None,
+                };
+                (low.saturating_add(l).saturating_add(1), high)
+            },
+        )
+    }
+}
+
+/// Drops repeated elements from a sorted iterator, created by
+/// [`dedup_sorted`] and [`dedup_sorted_by`].
+pub struct DedupSorted<I, F>
+where
+    I: Iterator,
+{
+    iter: Peekable<I>,
+    same: F,
+}
+
+/// Keeps the first of each run of equal elements of a sorted iterator.
+///
+/// ```
+/// use chonky_structures::sorting::merge::dedup_sorted;
+///
+/// let unique: Vec<_> = dedup_sorted([1, 1, 2, 3, 3, 3]).collect();
+/// assert_eq!(unique, [1, 2, 3]);
+/// ```
+pub fn dedup_sorted<I>(iter: I) -> DedupSorted<I::IntoIter, Same<I::Item>>
+where
+    I: IntoIterator,
+    I::Item: PartialEq,
+{
+    dedup_sorted_by(iter, PartialEq::eq)
+}
+
+/// Keeps the first of each run of elements that `same` says are equal to
+/// the first.
+pub fn dedup_sorted_by<I, F>(iter: I, same: F) -> DedupSorted<I::IntoIter, F>
+where
+    I: IntoIterator,
+    F: FnMut(&I::Item, &I::Item) -> bool,
+{
+    DedupSorted {
+        iter: iter.into_iter().peekable(),
+        same,
+    }
+}
+
+impl<I, F> Iterator for DedupSorted<I, F>
+where
+    I: Iterator,
+    F: FnMut(&I::Item, &I::Item) -> bool,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let item = self.iter.next()?;
+        while self.iter.next_if(|next| (self.same)(&item, next)).is_some() {}
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (low, high) = self.iter.size_hint();
+        (low.min(1), high)
+    }
+}
+
+/// Whether the elements of a slice or iterator are in order.
+///
+/// ```
+/// use chonky_structures::sorting::merge::is_sorted;
+///
+/// assert!(is_sorted(&[1, 2, 2, 3]));
+/// assert!(!is_sorted((0..5).rev()));
+/// ```
+pub fn is_sorted<I>(iter: I) -> bool
+where
+    I: IntoIterator,
+    I::Item: Ord,
+{
+    is_sorted_by(iter, Ord::cmp)
+}
+
+pub fn is_sorted_by<I, F>(iter: I, mut compare: F) -> bool
+where
+    I: IntoIterator,
+    F: FnMut(&I::Item, &I::Item) -> Ordering,
+{
+    let mut iter = iter.into_iter();
+    let Some(mut last) = iter.next() else {
+        return true;
+    };
+    for item in iter {
+        if compare(&last, &item) == Ordering::Greater {
+            return false;
+        }
+        last = item;
+    }
+    true
+}
+
+/// Merges the sorted runs `slice[..mid]` and `slice[mid..]` without
+/// allocating, keeping equal elements in order. Takes O(n log n)
+/// comparisons and swaps, see [`merge_sort`](super::merge_sort) for a merge
+/// that's linear but needs a buffer.
+///
+/// # Panics
+///
+/// Panics if `mid > slice.len()`.
+pub fn merge_in_place<T>(slice: &mut [T], mid: usize)
+where
+    T: Ord,
+{
+    merge_in_place_by(slice, mid, T::cmp);
+}
+
+pub fn merge_in_place_by<T, F>(slice: &mut [T], mid: usize, mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    assert!(mid <= slice.len(), "mid is out of bounds");
+    sym_merge(slice, mid, &mut compare, &Probe::OFF);
+}
+
+/// Owns the elements in `start..end` while they're out of the slice during a
+/// merge. Dropping it moves them into the gap at `dest`, so every element
+/// ends up back in the slice even if the comparator panics.
+#[cfg(feature = "alloc")]
+pub(super) struct MergeHole<T> {
+    pub(super) start: *mut T,
+    pub(super) end: *mut T,
+    pub(super) dest: *mut T,
+}
+
+#[cfg(feature = "alloc")]
+impl<T> MergeHole<T> {
+    /// The elements still to be moved into place.
+    unsafe fn remaining(&self) -> &[T] {
+        slice::from_raw_parts(self.start, self.end.offset_from(self.start) as usize)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T> Drop for MergeHole<T> {
+    fn drop(&mut self) {
+        unsafe {
+            let len = self.end.offset_from(self.start) as usize;
+            ptr::copy_nonoverlapping(self.start, self.dest, len);
+        }
+    }
+}
+
+/// Merges the sorted runs `slice[..mid]` and `slice[mid..]`, using `buf` to
+/// hold the shorter of the two. `buf` must have room for at least
+/// `min(mid, slice.len() - mid)` elements.
+#[cfg(feature = "alloc")]
+fn merge_buffered<T, F, O>(
+    slice: &mut [T],
+    mid: usize,
+    buf: &mut [MaybeUninit<T>],
+    compare: &mut F,
+    probe: &Probe<T, O>,
+) where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    let len = slice.len();
+    assert!(mid <= len && mid.min(len - mid) <= buf.len());
+
+    unsafe {
+        let v = slice.as_mut_ptr();
+        let buf = buf.as_mut_ptr() as *mut T;
+
+        if mid <= len - mid {
+            // Buffer the left run and merge front to back. The gap left in
+            // the slice is always as long as what's left in the buffer, so
+            // `hole.dest` never overtakes `right`.
+            ptr::copy_nonoverlapping(v, buf, mid);
+            let mut hole = MergeHole {
+                start: buf,
+                end: buf.add(mid),
+                dest: v,
+            };
+            let mut right = v.add(mid);
+            let right_end = v.add(len);
+
+            while hole.start < hole.end && right < right_end {
+                // take from the left on ties to keep the sort stable
+                let to_copy = if compare(&*right, &*hole.start) == Ordering::Less {
+                    let r = right;
+                    right = right.add(1);
+                    r
+                } else {
+                    let l = hole.start;
+                    hole.start = hole.start.add(1);
+                    l
+                };
+                ptr::copy_nonoverlapping(to_copy, hole.dest, 1);
+                probe.write(hole.dest, slice::from_ref(&*hole.dest));
+                hole.dest = hole.dest.add(1);
+            }
+            probe.write(hole.dest, hole.remaining());
+        } else {
+            // Buffer the right run and merge back to front, the mirror image
+            // of the above.
+            ptr::copy_nonoverlapping(v.add(mid), buf, len - mid);
+            let mut hole = MergeHole {
+                start: buf,
+                end: buf.add(len - mid),
+                dest: v.add(mid),
+            };
+            let mut out = v.add(len);
+
+            while v < hole.dest && hole.start < hole.end {
+                out = out.sub(1);
+                // take from the right on ties to keep the sort stable
+                if compare(&*hole.end.sub(1), &*hole.dest.sub(1)) == Ordering::Less {
+                    hole.dest = hole.dest.sub(1);
+                    ptr::copy_nonoverlapping(hole.dest, out, 1);
+                } else {
+                    hole.end = hole.end.sub(1);
+                    ptr::copy_nonoverlapping(hole.end, out, 1);
+                }
+                probe.write(out, slice::from_ref(&*out));
+            }
+            probe.write(hole.dest, hole.remaining());
+        }
+        // `hole` moves whatever is left in the buffer into place
+    }
+}
+
+/// Merges `slice[..mid]` and `slice[mid..]` unless they're already in order.
+#[cfg(feature = "alloc")]
+pub(super) fn merge_runs<T, F, O>(
+    slice: &mut [T],
+    mid: usize,
+    buf: &mut [MaybeUninit<T>],
+    compare: &mut F,
+    probe: &Probe<T, O>,
+) where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    if mid == 0 || mid == slice.len() {
+        return;
+    }
+    if compare(&slice[mid - 1], &slice[mid]) != Ordering::Greater {
+        return;
+    }
+    merge_buffered(slice, mid, buf, compare, probe);
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn kmerge_is_stable() {
+        let runs = [
+            vec![(1, 'a'), (3, 'a'), (3, 'b')],
+            vec![],
+            vec![(0, 'c'), (3, 'c'), (4, 'c')],
+            vec![(1, 'd'), (3, 'd')],
+        ];
+        let merged = kmerge_by(runs.clone(), |a, b| a.0.cmp(&b.0));
+        assert_eq!(merged.size_hint(), (8, Some(8)));
+        let merged: Vec<_> = merged.collect();
+        assert_eq!(
+            merged,
+            [
+                (0, 'c'),
+                (1, 'a'),
+                (1, 'd'),
+                (3, 'a'),
+                (3, 'b'),
+                (3, 'c'),
+                (3, 'd'),
+                (4, 'c'),
+            ]
+        );
+
+        let pair: Vec<_> = merge_by(&runs[0], &runs[3], |a, b| a.0.cmp(&b.0)).collect();
+        assert_eq!(
+            pair,
+            [&(1, 'a'), &(1, 'd'), &(3, 'a'), &(3, 'b'), &(3, 'd')]
+        );
+        assert_eq!(kmerge(Vec::<Vec<u8>>::new()).next(), None);
+    }
+
+    #[test]
+    fn dedup_and_is_sorted() {
+        let words = ["a", "A", "b", "B", "b", "c"];
+        let unique: Vec<_> = dedup_sorted_by(words, |a, b| a.eq_ignore_ascii_case(b)).collect();
+        assert_eq!(unique, ["a", "b", "c"]);
+        assert_eq!(dedup_sorted(Vec::<u8>::new()).next(), None);
+
+        assert!(is_sorted(Vec::<u8>::new()));
+        assert!(is_sorted(kmerge([0..10, 5..8, 3..20])));
+        assert!(is_sorted_by([3, 2, 2, 1], |a, b| b.cmp(a)));
+        assert!(!is_sorted([1, 3, 2]));
+    }
+
+    #[test]
+    fn merges_in_place() {
+        for len in 0..40 {
+            for mid in 0..=len {
+                // equal keys across both runs, tagged with where they started
+                let mut v: Vec<_> = (0..len).map(|i| ((i % mid.max(1)) / 3, i)).collect();
+                v[..mid].sort();
+                v[mid..].sort();
+                merge_in_place_by(&mut v, mid, |a, b| a.0.cmp(&b.0));
+                assert!(v.windows(2).all(|w| w[0] <= w[1]), "{len} {mid}");
+            }
+        }
+    }
+}
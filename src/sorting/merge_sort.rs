@@ -1,59 +1,136 @@
-use super::Sorter;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::mem::MaybeUninit;
 
+use super::instrument::{Observer, Probe};
+use super::merge::merge_runs;
+use super::{insertion_sort, Complexity, Properties, Sorter};
+
+/// Top-down (recursive) merge sort.
 pub struct MergeSort {}
 
-fn merge<T>(slice: &mut [T], split: usize) -> Vec<T>
+/// Bottom-up (iterative) merge sort.
+pub struct BottomUpMergeSort {}
+
+/// Runs up to this length are insertion sorted instead of being split further.
+/// Not with a sorting network like the quicksorts, since those aren't stable.
+pub(super) const MIN_RUN: usize = 16;
+
+pub fn sort<T>(slice: &mut [T])
 where
-    T: Ord + Clone,
+    T: Ord,
 {
-    let mut temp = Vec::with_capacity(slice.len());
-    let mut i = 0;
-    let mut j = split + 1;
-    while i < split && j < slice.len() {
-        if slice[i] < slice[j] {
-            temp.push(slice[i].clone());
-            i += 1;
-        } else {
-            temp.push(slice[j].clone());
-            j += 1;
-        }
-    }
+    sort_by(slice, T::cmp);
+}
+
+pub fn sort_by<T, F>(slice: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    top_down(slice, &mut compare, &Probe::OFF);
+}
 
-    while i < split {
-        temp.push(slice[i].clone());
-        i += 1;
+fn top_down<T, F, O>(slice: &mut [T], compare: &mut F, probe: &Probe<T, O>)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    if slice.len() <= MIN_RUN {
+        insertion_sort::insertion_sort(slice, compare, probe);
+        return;
     }
 
-    while j < slice.len() {
-        temp.push(slice[j].clone());
-        j += 1;
+    let mut buf = Vec::with_capacity(slice.len() / 2);
+    merge_sort(slice, buf.spare_capacity_mut(), compare, probe);
+}
+
+pub(super) fn merge_sort<T, F, O>(
+    slice: &mut [T],
+    buf: &mut [MaybeUninit<T>],
+    compare: &mut F,
+    probe: &Probe<T, O>,
+) where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    let entered = probe.enter(slice);
+    if slice.len() <= MIN_RUN {
+        insertion_sort::insertion_sort(slice, compare, probe);
+    } else {
+        let mid = slice.len() / 2;
+        merge_sort(&mut slice[..mid], buf, compare, probe);
+        merge_sort(&mut slice[mid..], buf, compare, probe);
+        merge_runs(slice, mid, buf, compare, probe);
     }
-    temp
+    probe.leave(entered);
 }
-pub fn sort<T>(slice: &mut [T])
+
+pub fn sort_bottom_up<T>(slice: &mut [T])
 where
-    T: Ord + Clone + core::fmt::Debug,
+    T: Ord,
 {
-    println!("{:#?}", slice);
-    match slice.len() {
-        0 | 1 => return,
-        2 => {
-            if slice[0] > slice[1] {
-                slice.swap(0, 1);
-            }
-            return;
-        }
-        _ => {
-            let mid = slice.len() / 2;
-            sort(&mut slice[..=mid]);
-            sort(&mut slice[(mid + 1)..]);
-            let ret = merge(slice, mid);
-            slice.clone_from_slice(&ret);
+    sort_bottom_up_by(slice, T::cmp);
+}
+
+pub fn sort_bottom_up_by<T, F>(slice: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    bottom_up(slice, &mut compare, &Probe::OFF);
+}
+
+fn bottom_up<T, F, O>(slice: &mut [T], compare: &mut F, probe: &Probe<T, O>)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    let len = slice.len();
+    for run in slice.chunks_mut(MIN_RUN) {
+        insertion_sort::insertion_sort(run, compare, probe);
+    }
+    if len <= MIN_RUN {
+        return;
+    }
+
+    let mut buf = Vec::with_capacity(len / 2);
+    let buf = buf.spare_capacity_mut();
+    let mut width = MIN_RUN;
+    while width < len {
+        for pair in slice.chunks_mut(2 * width) {
+            merge_runs(pair, width.min(pair.len()), buf, compare, probe);
         }
+        width *= 2;
     }
 }
 
-impl<T: Clone + core::fmt::Debug> Sorter<T> for MergeSort {
+pub(super) const PROPERTIES: Properties = Properties {
+    stable: true,
+    in_place: false,
+    requires_clone: false,
+    best: Complexity::Linear,
+    average: Complexity::Linearithmic,
+    worst: Complexity::Linearithmic,
+};
+
+impl<T> Sorter<T> for MergeSort {
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+        sort_by(slice, compare);
+    }
+
+    fn properties(&self) -> Properties {
+        PROPERTIES
+    }
+
+    fn sort_observed(
+        &self,
+        slice: &mut [T],
+        compare: &mut dyn FnMut(&T, &T) -> Ordering,
+        observer: &dyn Observer<T>,
+    ) {
+        let probe = Probe::new(slice, observer);
+        top_down(slice, &mut probe.observe(compare), &probe);
+    }
+
     fn sort(&self, slice: &mut [T])
     where
         T: Ord,
@@ -61,3 +138,92 @@ impl<T: Clone + core::fmt::Debug> Sorter<T> for MergeSort {
         sort(slice);
     }
 }
+
+impl<T> Sorter<T> for BottomUpMergeSort {
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+        sort_bottom_up_by(slice, compare);
+    }
+
+    fn properties(&self) -> Properties {
+        PROPERTIES
+    }
+
+    fn sort_observed(
+        &self,
+        slice: &mut [T],
+        compare: &mut dyn FnMut(&T, &T) -> Ordering,
+        observer: &dyn Observer<T>,
+    ) {
+        let probe = Probe::new(slice, observer);
+        bottom_up(slice, &mut probe.observe(compare), &probe);
+    }
+
+    fn sort(&self, slice: &mut [T])
+    where
+        T: Ord,
+    {
+        sort_bottom_up(slice);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::vec::Vec;
+    use core::cmp::Ordering;
+    use std::panic::{self, AssertUnwindSafe};
+
+    #[test]
+    fn long_input() {
+        let input: Vec<u32> = (0..1000).map(|i| (i * 7919) % 1009).collect();
+        let mut expected = input.clone();
+        expected.sort();
+
+        let mut top_down = input.clone();
+        super::sort(&mut top_down);
+        assert_eq!(top_down, expected);
+
+        let mut bottom_up = input.clone();
+        super::sort_bottom_up(&mut bottom_up);
+        assert_eq!(bottom_up, expected);
+
+        let pairs: Vec<_> = input.iter().map(|k| k % 10).enumerate().collect();
+        let mut expected = pairs.clone();
+        expected.sort_by_key(|p| p.1);
+        let mut top_down = pairs.clone();
+        super::sort_by(&mut top_down, |a, b| a.1.cmp(&b.1));
+        assert_eq!(top_down, expected);
+        let mut bottom_up = pairs;
+        super::sort_bottom_up_by(&mut bottom_up, |a, b| a.1.cmp(&b.1));
+        assert_eq!(bottom_up, expected);
+    }
+
+    fn check_panic_safety(
+        sort: impl Fn(&mut [Box<u32>], &mut dyn FnMut(&Box<u32>, &Box<u32>) -> Ordering),
+    ) {
+        let mut list: Vec<_> = (0..200).rev().map(Box::new).collect();
+        let mut calls = 0;
+        let ret = panic::catch_unwind(AssertUnwindSafe(|| {
+            sort(&mut list, &mut |a, b| {
+                calls += 1;
+                if calls == 500 {
+                    panic!("comparator gave up");
+                }
+                a.cmp(b)
+            })
+        }));
+        assert!(ret.is_err());
+
+        // a duplicated or lost element would show up here, or as a double
+        // free when `list` is dropped
+        let mut values: Vec<_> = list.iter().map(|b| **b).collect();
+        values.sort();
+        assert_eq!(values, (0..200).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn panicking_comparator_keeps_every_element() {
+        check_panic_safety(|s, c| super::sort_by(s, c));
+        check_panic_safety(|s, c| super::sort_bottom_up_by(s, c));
+    }
+}
@@ -0,0 +1,751 @@
+//! Sorting networks for small slices.
+//!
+//! A sorting network is a fixed sequence of compare-exchanges, so it makes
+//! the same comparisons whatever the input and has no branches to
+//! mispredict beyond the exchanges themselves. The networks here are built
+//! at compile time for up to [`MAX_LEN`] elements: the smallest known
+//! networks up to 16 elements, and Batcher's odd-even merge sort above that,
+//! which isn't the smallest possible.
+//!
+//! Networks aren't stable, two equal elements can be exchanged past each
+//! other.
+
+use core::cmp::Ordering;
+
+use super::instrument::{Observer, Probe};
+
+/// The longest slice a network is built for.
+pub const MAX_LEN: usize = 32;
+
+/// Enough room for the biggest network, a bitonic sort of 32 elements.
+const MAX_PAIRS: usize = 240;
+
+/// A sorting network of compare-exchanges `(i, j)`, each of which puts the
+/// smaller of the elements at `i` and `j` at `i`, where `i < j`.
+#[derive(Copy, Clone, Debug)]
+pub struct Network {
+    pairs: [(u8, u8); MAX_PAIRS],
+    len: usize,
+}
+
+/// The networks with the fewest comparators known, which are proven to be
+/// the smallest possible up to 12 elements.
+const BEST_KNOWN: [&[(u8, u8)]; 17] = [
+    &[],
+    &[],
+    &[(0, 1)],
+    &[(0, 2), (0, 1), (1, 2)],
+    &[(0, 2), (1, 3), (0, 1), (2, 3), (1, 2)],
+    &[
+        (0, 3),
+        (1, 4),
+        (0, 2),
+        (1, 3),
+        (0, 1),
+        (2, 4),
+        (1, 2),
+        (3, 4),
+        (2, 3),
+    ],
+    &[
+        (0, 5),
+        (1, 3),
+        (2, 4),
+        (1, 2),
+        (3, 4),
+        (0, 3),
+        (2, 5),
+        (0, 1),
+        (2, 3),
+        (4, 5),
+        (1, 2),
+        (3, 4),
+    ],
+    &[
+        (0, 6),
+        (2, 3),
+        (4, 5),
+        (0, 2),
+        (1, 4),
+        (3, 6),
+        (0, 1),
+        (2, 5),
+        (3, 4),
+        (1, 2),
+        (4, 6),
+        (2, 3),
+        (4, 5),
+        (1, 2),
+        (3, 4),
+        (5, 6),
+    ],
+    &[
+        (0, 2),
+        (1, 3),
+        (4, 6),
+        (5, 7),
+        (0, 4),
+        (1, 5),
+        (2, 6),
+        (3, 7),
+        (0, 1),
+        (2, 3),
+        (4, 5),
+        (6, 7),
+        (2, 4),
+        (3, 5),
+        (1, 4),
+        (3, 6),
+        (1, 2),
+        (3, 4),
+        (5, 6),
+    ],
+    &[
+        (0, 3),
+        (1, 7),
+        (2, 5),
+        (4, 8),
+        (0, 7),
+        (2, 4),
+        (3, 8),
+        (5, 6),
+        (0, 2),
+        (1, 3),
+        (4, 5),
+        (7, 8),
+        (1, 4),
+        (3, 6),
+        (5, 7),
+        (0, 1),
+        (2, 4),
+        (3, 5),
+        (6, 8),
+        (2, 3),
+        (4, 5),
+        (6, 7),
+        (1, 2),
+        (3, 4),
+        (5, 6),
+    ],
+    &[
+        (0, 8),
+        (1, 9),
+        (2, 7),
+        (3, 5),
+        (4, 6),
+        (0, 2),
+        (1, 4),
+        (5, 8),
+        (7, 9),
+        (0, 3),
+        (2, 4),
+        (5, 7),
+        (6, 9),
+        (0, 1),
+        (3, 6),
+        (8, 9),
+        (1, 5),
+        (2, 3),
+        (4, 8),
+        (6, 7),
+        (1, 2),
+        (3, 5),
+        (4, 6),
+        (7, 8),
+        (2, 3),
+        (4, 5),
+        (6, 7),
+        (3, 4),
+        (5, 6),
+    ],
+    &[
+        (0, 9),
+        (1, 6),
+        (2, 4),
+        (3, 7),
+        (5, 8),
+        (0, 1),
+        (3, 5),
+        (4, 10),
+        (6, 9),
+        (7, 8),
+        (1, 3),
+        (2, 5),
+        (4, 7),
+        (8, 10),
+        (0, 4),
+        (1, 2),
+        (3, 7),
+        (5, 9),
+        (6, 8),
+        (0, 1),
+        (2, 6),
+        (4, 5),
+        (7, 8),
+        (9, 10),
+        (2, 4),
+        (3, 6),
+        (5, 7),
+        (8, 9),
+        (1, 2),
+        (3, 4),
+        (5, 6),
+        (7, 8),
+        (2, 3),
+        (4, 5),
+        (6, 7),
+    ],
+    &[
+        (0, 8),
+        (1, 7),
+        (2, 6),
+        (3, 11),
+        (4, 10),
+        (5, 9),
+        (0, 1),
+        (2, 5),
+        (3, 4),
+        (6, 9),
+        (7, 8),
+        (10, 11),
+        (0, 2),
+        (1, 6),
+        (5, 10),
+        (9, 11),
+        (0, 3),
+        (1, 2),
+        (4, 6),
+        (5, 7),
+        (8, 11),
+        (9, 10),
+        (1, 4),
+        (3, 5),
+        (6, 8),
+        (7, 10),
+        (1, 3),
+        (2, 5),
+        (6, 9),
+        (8, 10),
+        (2, 3),
+        (4, 5),
+        (6, 7),
+        (8, 9),
+        (4, 6),
+        (5, 7),
+        (3, 4),
+        (5, 6),
+        (7, 8),
+    ],
+    &[
+        (0, 12),
+        (1, 10),
+        (2, 9),
+        (3, 7),
+        (5, 11),
+        (6, 8),
+        (1, 6),
+        (2, 3),
+        (4, 11),
+        (7, 9),
+        (8, 10),
+        (0, 4),
+        (1, 2),
+        (3, 6),
+        (7, 8),
+        (9, 10),
+        (11, 12),
+        (4, 6),
+        (5, 9),
+        (8, 11),
+        (10, 12),
+        (0, 5),
+        (3, 8),
+        (4, 7),
+        (6, 11),
+        (9, 10),
+        (0, 1),
+        (2, 5),
+        (6, 9),
+        (7, 8),
+        (10, 11),
+        (1, 3),
+        (2, 4),
+        (5, 6),
+        (9, 10),
+        (1, 2),
+        (3, 4),
+        (5, 7),
+        (6, 8),
+        (2, 3),
+        (4, 5),
+        (6, 7),
+        (8, 9),
+        (3, 4),
+        (5, 6),
+    ],
+    &[
+        (0, 13),
+        (1, 12),
+        (4, 8),
+        (5, 6),
+        (7, 11),
+        (9, 10),
+        (0, 5),
+        (1, 7),
+        (2, 9),
+        (3, 4),
+        (6, 13),
+        (11, 12),
+        (0, 1),
+        (2, 3),
+        (4, 5),
+        (6, 8),
+        (7, 9),
+        (10, 11),
+        (12, 13),
+        (0, 2),
+        (1, 3),
+        (4, 10),
+        (5, 11),
+        (6, 7),
+        (8, 9),
+        (1, 2),
+        (3, 12),
+        (4, 6),
+        (5, 7),
+        (8, 10),
+        (9, 11),
+        (1, 4),
+        (2, 6),
+        (5, 8),
+        (7, 10),
+        (9, 13),
+        (2, 4),
+        (3, 6),
+        (9, 12),
+        (11, 13),
+        (3, 5),
+        (6, 8),
+        (7, 9),
+        (10, 12),
+        (3, 4),
+        (5, 6),
+        (7, 8),
+        (9, 10),
+        (11, 12),
+        (6, 7),
+        (8, 9),
+    ],
+    &[
+        (0, 13),
+        (1, 12),
+        (3, 14),
+        (4, 8),
+        (5, 6),
+        (7, 11),
+        (9, 10),
+        (0, 5),
+        (1, 7),
+        (2, 9),
+        (3, 4),
+        (6, 13),
+        (8, 14),
+        (11, 12),
+        (0, 1),
+        (2, 3),
+        (4, 5),
+        (6, 8),
+        (7, 9),
+        (10, 11),
+        (12, 13),
+        (0, 2),
+        (1, 3),
+        (4, 10),
+        (5, 11),
+        (6, 7),
+        (8, 9),
+        (12, 14),
+        (1, 2),
+        (3, 12),
+        (4, 6),
+        (5, 7),
+        (8, 10),
+        (9, 11),
+        (13, 14),
+        (1, 4),
+        (2, 6),
+        (5, 8),
+        (7, 10),
+        (9, 13),
+        (11, 14),
+        (2, 4),
+        (3, 6),
+        (9, 12),
+        (11, 13),
+        (3, 5),
+        (6, 8),
+        (7, 9),
+        (10, 12),
+        (3, 4),
+        (5, 6),
+        (7, 8),
+        (9, 10),
+        (11, 12),
+        (6, 7),
+        (8, 9),
+    ],
+    &[
+        (0, 13),
+        (1, 12),
+        (2, 15),
+        (3, 14),
+        (4, 8),
+        (5, 6),
+        (7, 11),
+        (9, 10),
+        (0, 5),
+        (1, 7),
+        (2, 9),
+        (3, 4),
+        (6, 13),
+        (8, 14),
+        (10, 15),
+        (11, 12),
+        (0, 1),
+        (2, 3),
+        (4, 5),
+        (6, 8),
+        (7, 9),
+        (10, 11),
+        (12, 13),
+        (14, 15),
+        (0, 2),
+        (1, 3),
+        (4, 10),
+        (5, 11),
+        (6, 7),
+        (8, 9),
+        (12, 14),
+        (13, 15),
+        (1, 2),
+        (3, 12),
+        (4, 6),
+        (5, 7),
+        (8, 10),
+        (9, 11),
+        (13, 14),
+        (1, 4),
+        (2, 6),
+        (5, 8),
+        (7, 10),
+        (9, 13),
+        (11, 14),
+        (2, 4),
+        (3, 6),
+        (9, 12),
+        (11, 13),
+        (3, 5),
+        (6, 8),
+        (7, 9),
+        (10, 12),
+        (3, 4),
+        (5, 6),
+        (7, 8),
+        (9, 10),
+        (11, 12),
+        (6, 7),
+        (8, 9),
+    ],
+];
+
+impl Network {
+    const EMPTY: Network = Network {
+        pairs: [(0, 0); MAX_PAIRS],
+        len: 0,
+    };
+
+    const fn push(&mut self, i: usize, j: usize) {
+        self.pairs[self.len] = (i as u8, j as u8);
+        self.len += 1;
+    }
+
+    /// The smallest network this module knows for `n` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than [`MAX_LEN`].
+    pub const fn best(n: usize) -> Network {
+        if n < BEST_KNOWN.len() {
+            let mut network = Network::EMPTY;
+            let pairs = BEST_KNOWN[n];
+            let mut k = 0;
+            while k < pairs.len() {
+                network.push(pairs[k].0 as usize, pairs[k].1 as usize);
+                k += 1;
+            }
+            network
+        } else {
+            Network::odd_even_merge(n)
+        }
+    }
+
+    /// Batcher's odd-even merge sort, which works for any `n` by leaving out
+    /// the comparators past the end of the next power of two.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than [`MAX_LEN`].
+    pub const fn odd_even_merge(n: usize) -> Network {
+        assert!(n <= MAX_LEN, "networks only go up to 32 elements");
+        let mut network = Network::EMPTY;
+        let mut p = 1;
+        while p < n {
+            let mut k = p;
+            while k >= 1 {
+                let mut j = k % p;
+                while j + k < n {
+                    let mut i = 0;
+                    while i < k && i + j + k < n {
+                        // only compare within the same pair of runs of `p`
+                        if (i + j) / (2 * p) == (i + j + k) / (2 * p) {
+                            network.push(i + j, i + j + k);
+                        }
+                        i += 1;
+                    }
+                    j += 2 * k;
+                }
+                k /= 2;
+            }
+            p *= 2;
+        }
+        network
+    }
+
+    /// Batcher's bitonic sort, with every other half flipped so that all the
+    /// comparators point the same way.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` isn't a power of two no greater than [`MAX_LEN`].
+    pub const fn bitonic(n: usize) -> Network {
+        assert!(n <= MAX_LEN, "networks only go up to 32 elements");
+        assert!(n.is_power_of_two(), "bitonic networks need a power of two");
+        let mut network = Network::EMPTY;
+        let mut k = 2;
+        while k <= n {
+            let mut i = 0;
+            while i < n {
+                let mut j = 0;
+                while j < k / 2 {
+                    network.push(i + j, i + k - 1 - j);
+                    j += 1;
+                }
+                i += k;
+            }
+            let mut m = k / 4;
+            while m >= 1 {
+                let mut i = 0;
+                while i < n {
+                    let mut j = 0;
+                    while j < m {
+                        network.push(i + j, i + j + m);
+                        j += 1;
+                    }
+                    i += 2 * m;
+                }
+                m /= 2;
+            }
+            k *= 2;
+        }
+        network
+    }
+
+    pub fn pairs(&self) -> &[(u8, u8)] {
+        &self.pairs[..self.len]
+    }
+
+    /// Sorts `slice` according to `compare`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slice` is shorter than the network.
+    pub fn sort_by<T, F>(&self, slice: &mut [T], mut compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        self.apply(slice, &mut compare, &Probe::OFF);
+    }
+
+    fn apply<T, F, O>(&self, slice: &mut [T], compare: &mut F, probe: &Probe<T, O>)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+        O: Observer<T> + ?Sized,
+    {
+        for &(i, j) in self.pairs() {
+            let (i, j) = (i as usize, j as usize);
+            if compare(&slice[j], &slice[i]) == Ordering::Less {
+                probe.swap(slice, i, j);
+            }
+        }
+    }
+}
+
+/// The best network for every length up to [`MAX_LEN`].
+const NETWORKS: [Network; MAX_LEN + 1] = {
+    let mut networks = [Network::EMPTY; MAX_LEN + 1];
+    let mut n = 0;
+    while n <= MAX_LEN {
+        networks[n] = Network::best(n);
+        n += 1;
+    }
+    networks
+};
+
+/// Holds the network for arrays of `N` elements, so using one for an `N`
+/// over [`MAX_LEN`] fails to compile.
+struct ForLen<const N: usize>;
+
+impl<const N: usize> ForLen<N> {
+    const NETWORK: &'static Network = {
+        assert!(N <= MAX_LEN, "networks only go up to 32 elements");
+        &NETWORKS[N]
+    };
+}
+
+/// Sorts an array of up to [`MAX_LEN`] elements with a sorting network.
+///
+/// ```
+/// use chonky_structures::sorting::network::sort_network;
+///
+/// let mut array = [3, 1, 2];
+/// sort_network::<3, _>(&mut array);
+/// assert_eq!(array, [1, 2, 3]);
+/// ```
+pub fn sort_network<const N: usize, T>(array: &mut [T; N])
+where
+    T: Ord,
+{
+    sort_network_by(array, T::cmp);
+}
+
+pub fn sort_network_by<const N: usize, T, F>(array: &mut [T; N], compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    ForLen::<N>::NETWORK.sort_by(array, compare);
+}
+
+/// Sorts an array of up to [`MAX_LEN`] `Copy` elements with a sorting
+/// network, computing the minimum and maximum of each pair instead of
+/// branching on whether to exchange them. This is fastest for primitives,
+/// where the compiler can turn the selects into conditional moves.
+pub fn sort_network_branchless<const N: usize, T>(array: &mut [T; N])
+where
+    T: Copy + Ord,
+{
+    for &(i, j) in ForLen::<N>::NETWORK.pairs() {
+        let (i, j) = (i as usize, j as usize);
+        let (a, b) = (array[i], array[j]);
+        let less = b < a;
+        array[i] = if less { b } else { a };
+        array[j] = if less { a } else { b };
+    }
+}
+
+/// Sorts a slice of up to [`MAX_LEN`] elements, the base case of the
+/// quicksorts.
+pub(super) fn sort_small<T, F, O>(slice: &mut [T], compare: &mut F, probe: &Probe<T, O>)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    NETWORKS[slice.len()].apply(slice, compare, probe);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorting::quick_sort;
+
+    /// By the 0-1 principle a network sorts everything if it sorts every
+    /// sequence of zeros and ones. The sequences are checked 64 at a time,
+    /// bit `k` of `lines[i]` being element `i` of sequence `k`, which makes
+    /// a compare-exchange an and and an or.
+    fn sorts_all_bits(network: &Network, n: usize) -> bool {
+        let sequences = 1u64 << n;
+        let mask = if n < 6 {
+            (1 << sequences) - 1
+        } else {
+            u64::MAX
+        };
+        (0..sequences.div_ceil(64)).all(|word| {
+            let mut lines = [0u64; MAX_LEN];
+            for (i, line) in lines[..n].iter_mut().enumerate() {
+                *line = (0..64)
+                    .filter(|k| (word * 64 + k) >> i & 1 == 1)
+                    .fold(0, |line, k| line | 1 << k);
+            }
+            for &(i, j) in network.pairs() {
+                let (a, b) = (lines[i as usize], lines[j as usize]);
+                lines[i as usize] = a & b;
+                lines[j as usize] = a | b;
+            }
+            lines[..n].windows(2).all(|w| w[0] & !w[1] & mask == 0)
+        })
+    }
+
+    #[test]
+    fn networks_sort() {
+        // dropping the last comparator of a network breaks it
+        for n in [3, 16, 20] {
+            let mut broken = NETWORKS[n];
+            broken.len -= 1;
+            assert!(!sorts_all_bits(&broken, n), "broken {n}");
+        }
+        // every network the quicksorts and selection use
+        let used = NETWORKS
+            .iter()
+            .enumerate()
+            .take(quick_sort::MAX_NETWORK + 1);
+        for (n, network) in used {
+            assert!(sorts_all_bits(network, n), "used {n}");
+        }
+        for n in 0..=16 {
+            assert!(sorts_all_bits(&Network::best(n), n), "best {n}");
+            assert!(
+                sorts_all_bits(&Network::odd_even_merge(n), n),
+                "odd-even {n}"
+            );
+        }
+        for n in [1, 2, 4, 8, 16] {
+            assert!(sorts_all_bits(&Network::bitonic(n), n), "bitonic {n}");
+        }
+        let sizes = [0, 0, 1, 3, 5, 9, 12, 16, 19, 25, 29, 35, 39, 45, 51, 56, 60];
+        for (n, size) in sizes.into_iter().enumerate() {
+            assert_eq!(Network::best(n).pairs().len(), size);
+        }
+        assert_eq!(Network::odd_even_merge(32).pairs().len(), 191);
+        assert_eq!(Network::bitonic(32).pairs().len(), MAX_PAIRS);
+    }
+
+    #[test]
+    fn arrays() {
+        let mut state = 1u32;
+        let mut random = || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            state >> 16
+        };
+        for _ in 0..1000 {
+            let mut array: [u32; 32] = core::array::from_fn(|_| random() % 50);
+            let mut copy = array;
+            sort_network(&mut array);
+            assert!(array.windows(2).all(|w| w[0] <= w[1]));
+            sort_network_branchless(&mut copy);
+            assert_eq!(array, copy);
+
+            let mut array: [u32; 23] = core::array::from_fn(|_| random());
+            sort_network_by(&mut array, |a, b| b.cmp(a));
+            assert!(array.windows(2).all(|w| w[0] >= w[1]));
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::mem;
+use core::mem::MaybeUninit;
+use core::num::NonZeroUsize;
+use std::thread::{self, Scope};
+
+use super::instrument::Probe;
+use super::quick_sort::{State, Step};
+use super::{insertion_sort, merge, merge_sort, quick_sort, Properties, Sorter};
+
+/// Slices up to this length are sorted on a single thread by default.
+const DEFAULT_GRAIN: usize = 1 << 13;
+
+/// [`MergeSort`](super::MergeSort) that sorts the two halves of the slice on
+/// separate threads, down to slices of `grain` elements.
+///
+/// The comparator given to [`Sorter::sort_by_dyn`] can't be shared between
+/// threads, so that sorts on the calling thread. [`Sorter::sort`] and
+/// [`ParallelMergeSort::par_sort_by`] use up to `threads` threads.
+#[derive(Copy, Clone, Debug)]
+pub struct ParallelMergeSort {
+    /// How many threads to sort with, 0 meaning one per available core.
+    pub threads: usize,
+    /// Slices up to this length are sorted on a single thread.
+    pub grain: usize,
+}
+
+/// [`QuickSort`](super::QuickSort) that sorts the two sides of each
+/// partition on separate threads, down to slices of `grain` elements.
+///
+/// It makes the same choices as the sequential sort, so equal elements end
+/// up in the same order. See [`ParallelMergeSort`] for which methods use
+/// more than one thread.
+#[derive(Copy, Clone, Debug)]
+pub struct ParallelQuickSort {
+    /// How many threads to sort with, 0 meaning one per available core.
+    pub threads: usize,
+    /// Slices up to this length are sorted on a single thread.
+    pub grain: usize,
+}
+
+impl Default for ParallelMergeSort {
+    fn default() -> Self {
+        Self {
+            threads: 0,
+            grain: DEFAULT_GRAIN,
+        }
+    }
+}
+
+impl Default for ParallelQuickSort {
+    fn default() -> Self {
+        Self {
+            threads: 0,
+            grain: DEFAULT_GRAIN,
+        }
+    }
+}
+
+fn thread_count(threads: usize) -> usize {
+    if threads == 0 {
+        thread::available_parallelism().map_or(1, NonZeroUsize::get)
+    } else {
+        threads
+    }
+}
+
+impl ParallelMergeSort {
+    pub fn par_sort_by<T, F>(&self, slice: &mut [T], compare: F)
+    where
+        T: Send,
+        F: Fn(&T, &T) -> Ordering + Sync,
+    {
+        if slice.len() <= merge_sort::MIN_RUN {
+            insertion_sort::sort_by(slice, compare);
+            return;
+        }
+
+        let mut buf = Vec::with_capacity(slice.len() / 2);
+        let grain = self.grain.max(merge_sort::MIN_RUN);
+        par_merge_sort(
+            slice,
+            buf.spare_capacity_mut(),
+            &compare,
+            thread_count(self.threads),
+            grain,
+        );
+    }
+}
+
+/// Splits the slice exactly like the sequential merge sort does, so each
+/// half needs at most half of `buf`.
+fn par_merge_sort<T, F>(
+    slice: &mut [T],
+    buf: &mut [MaybeUninit<T>],
+    compare: &F,
+    threads: usize,
+    grain: usize,
+) where
+    T: Send,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    if threads <= 1 || slice.len() <= grain {
+        merge_sort::merge_sort(slice, buf, &mut |a, b| compare(a, b), &Probe::OFF);
+        return;
+    }
+
+    let mid = slice.len() / 2;
+    let (left, right) = slice.split_at_mut(mid);
+    let (left_buf, right_buf) = buf.split_at_mut(mid / 2);
+    thread::scope(|s| {
+        s.spawn(|| par_merge_sort(left, left_buf, compare, threads / 2, grain));
+        par_merge_sort(right, right_buf, compare, threads - threads / 2, grain);
+    });
+    merge::merge_runs(slice, mid, buf, &mut |a, b| compare(a, b), &Probe::OFF);
+}
+
+impl ParallelQuickSort {
+    pub fn par_sort_by<T, F>(&self, slice: &mut [T], compare: F)
+    where
+        T: Send,
+        F: Fn(&T, &T) -> Ordering + Sync,
+    {
+        if slice.len() <= 1 {
+            return;
+        }
+
+        let state = State::new(slice.len());
+        let threads = thread_count(self.threads);
+        thread::scope(|s| par_quick_sort(s, slice, false, &compare, state, threads, self.grain));
+    }
+}
+
+/// Splits off the predecessor at the front of `slice`, if it has one.
+fn split_pred<T>(slice: &mut [T], has_pred: bool) -> (Option<&T>, &mut [T]) {
+    if has_pred {
+        let (pred, rest) = slice
+            .split_first_mut()
+            .expect("predecessor is in the slice");
+        (Some(&*pred), rest)
+    } else {
+        (None, slice)
+    }
+}
+
+/// The parallel version of [`quick_sort::quick_sort`]. A reference to the
+/// predecessor can't be handed to another thread without `T: Sync`, so it's
+/// kept at the front of `slice` instead, when `has_pred` is set.
+fn par_quick_sort<'scope, T, F>(
+    scope: &'scope Scope<'scope, '_>,
+    mut slice: &'scope mut [T],
+    mut has_pred: bool,
+    compare: &'scope F,
+    mut state: State,
+    mut threads: usize,
+    grain: usize,
+) where
+    T: Send,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let mut compare_mut = |a: &T, b: &T| compare(a, b);
+    loop {
+        let offset = has_pred as usize;
+        let len = slice.len() - offset;
+        let (pred, rest) = split_pred(slice, has_pred);
+        if threads <= 1 || len <= grain {
+            quick_sort::quick_sort(rest, &mut compare_mut, pred, state, &Probe::OFF);
+            return;
+        }
+
+        match quick_sort::step(rest, &mut compare_mut, pred, &mut state, &Probe::OFF) {
+            Step::Sorted => return,
+            Step::Skip(mid) => {
+                // the last of the skipped elements is equal to the
+                // predecessor, so it can stand in for it
+                slice = &mut mem::take(&mut slice)[offset + mid - 1..];
+                has_pred = true;
+            }
+            Step::Split(mid) => {
+                // the pivot ends up at the front of `right`, where it's the
+                // predecessor of everything after it
+                let (left, right) = mem::take(&mut slice).split_at_mut(offset + mid);
+                let (short, short_has_pred, long, long_has_pred) = if mid < len - mid - 1 {
+                    (left, has_pred, right, true)
+                } else {
+                    (right, true, left, has_pred)
+                };
+
+                if short.len() - short_has_pred as usize <= grain {
+                    let (pred, short) = split_pred(short, short_has_pred);
+                    quick_sort::quick_sort(
+                        short,
+                        &mut compare_mut,
+                        pred,
+                        state.fresh(),
+                        &Probe::OFF,
+                    );
+                } else {
+                    let short_threads = threads / 2;
+                    let short_state = state.fresh();
+                    scope.spawn(move || {
+                        par_quick_sort(
+                            scope,
+                            short,
+                            short_has_pred,
+                            compare,
+                            short_state,
+                            short_threads,
+                            grain,
+                        )
+                    });
+                    threads -= short_threads;
+                }
+                slice = long;
+                has_pred = long_has_pred;
+            }
+        }
+    }
+}
+
+impl<T: Send> Sorter<T> for ParallelMergeSort {
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+        merge_sort::sort_by(slice, compare);
+    }
+
+    fn properties(&self) -> Properties {
+        merge_sort::PROPERTIES
+    }
+
+    fn sort(&self, slice: &mut [T])
+    where
+        T: Ord,
+    {
+        self.par_sort_by(slice, T::cmp);
+    }
+}
+
+impl<T: Send> Sorter<T> for ParallelQuickSort {
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+        quick_sort::sort_by(slice, compare);
+    }
+
+    fn properties(&self) -> Properties {
+        quick_sort::PROPERTIES
+    }
+
+    fn sort(&self, slice: &mut [T])
+    where
+        T: Ord,
+    {
+        self.par_sort_by(slice, T::cmp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParallelMergeSort, ParallelQuickSort};
+    use alloc::vec::Vec;
+
+    fn inputs(n: u64) -> Vec<Vec<(u64, u64)>> {
+        let keyed = |f: &dyn Fn(u64) -> u64| (0..n).map(|i| (f(i), i)).collect();
+        Vec::from([
+            keyed(&|i| i),
+            keyed(&|i| n - i),
+            keyed(&|i| i % 7),
+            keyed(&|i| (i * 2_654_435_761) % 1_000_003),
+            keyed(&|i| (i * 2_654_435_761) % 1009),
+            keyed(&|i| if i < n / 2 { i } else { n - i }),
+        ])
+    }
+
+    // Only the keys are compared, so for the unstable sort the indices show
+    // whether equal keys ended up in the same order.
+    #[test]
+    fn matches_sequential_sorts() {
+        for input in inputs(100_000) {
+            let mut merge = input.clone();
+            super::merge_sort::sort_by(&mut merge, |a, b| a.0.cmp(&b.0));
+            let mut quick = input.clone();
+            super::quick_sort::sort_by(&mut quick, |a, b| a.0.cmp(&b.0));
+
+            for (threads, grain) in [(2, 1000), (3, 100), (8, 20), (0, 1 << 13)] {
+                let mut list = input.clone();
+                ParallelMergeSort { threads, grain }.par_sort_by(&mut list, |a, b| a.0.cmp(&b.0));
+                assert_eq!(list, merge);
+
+                let mut list = input.clone();
+                ParallelQuickSort { threads, grain }.par_sort_by(&mut list, |a, b| a.0.cmp(&b.0));
+                assert_eq!(list, quick);
+            }
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Sorting by permutation: finding the order a slice sorts into without
+//! moving it, and moving slices into a given order.
+//!
+//! A permutation here is a list of indices where `perm[i]` is the index of
+//! the element that belongs at `i`, which is what [`argsort`] returns.
+
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use super::Sorter;
+
+/// The indices of `slice` in the order that sorts it, found with `sorter`.
+/// Equal elements keep their order if `sorter` is stable.
+///
+/// ```
+/// use chonky_structures::sorting::{permutation::argsort, MergeSort};
+///
+/// assert_eq!(argsort(&["c", "a", "b"], &MergeSort {}), [1, 2, 0]);
+/// ```
+pub fn argsort<T, S>(slice: &[T], sorter: &S) -> Vec<usize>
+where
+    T: Ord,
+    S: Sorter<usize> + ?Sized,
+{
+    argsort_by(slice, sorter, T::cmp)
+}
+
+pub fn argsort_by<T, S, F>(slice: &[T], sorter: &S, mut compare: F) -> Vec<usize>
+where
+    S: Sorter<usize> + ?Sized,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let mut indices: Vec<usize> = (0..slice.len()).collect();
+    sorter.sort_by_dyn(&mut indices, &mut |a, b| compare(&slice[*a], &slice[*b]));
+    indices
+}
+
+/// Moves the element at `perm[i]` to `i` for every `i`, by following each
+/// cycle of the permutation, and leaves `perm` as the identity permutation.
+///
+/// ```
+/// use chonky_structures::sorting::permutation::apply_permutation_in_place;
+///
+/// let mut letters = ['c', 'a', 'b'];
+/// apply_permutation_in_place(&mut letters, &mut [1, 2, 0]);
+/// assert_eq!(letters, ['a', 'b', 'c']);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `perm` isn't a permutation of `0..slice.len()`, leaving both in
+/// an unspecified order.
+pub fn apply_permutation_in_place<T>(slice: &mut [T], perm: &mut [usize]) {
+    assert_eq!(slice.len(), perm.len(), "permutation has the wrong length");
+    for start in 0..perm.len() {
+        let mut current = start;
+        while perm[current] != start {
+            let next = perm[current];
+            // everything already in place points at itself, so landing on
+            // one of those means an index was repeated
+            assert_ne!(next, current, "not a permutation");
+            slice.swap(current, next);
+            perm[current] = current;
+            current = next;
+        }
+        perm[current] = current;
+    }
+}
+
+/// The permutation that undoes `perm`, so that `inverse[perm[i]] == i`.
+///
+/// # Panics
+///
+/// Panics if `perm` isn't a permutation of `0..perm.len()`.
+pub fn inverse_permutation(perm: &[usize]) -> Vec<usize> {
+    let mut inverse = vec![usize::MAX; perm.len()];
+    for (i, &p) in perm.iter().enumerate() {
+        assert!(inverse[p] == usize::MAX, "not a permutation");
+        inverse[p] = i;
+    }
+    inverse
+}
+
+/// A column that [`sort_together`] can put into the same order as the keys.
+pub trait Reorder {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reorders the column as [`apply_permutation_in_place`] does, leaving
+    /// `perm` as the identity permutation.
+    fn reorder(&mut self, perm: &mut [usize]);
+}
+
+impl<T> Reorder for Vec<T> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn reorder(&mut self, perm: &mut [usize]) {
+        apply_permutation_in_place(self, perm);
+    }
+}
+
+impl<T, const N: usize> Reorder for [T; N] {
+    fn len(&self) -> usize {
+        N
+    }
+
+    fn reorder(&mut self, perm: &mut [usize]) {
+        apply_permutation_in_place(self, perm);
+    }
+}
+
+impl<T> Reorder for VecDeque<T> {
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+
+    fn reorder(&mut self, perm: &mut [usize]) {
+        apply_permutation_in_place(self.make_contiguous(), perm);
+    }
+}
+
+/// Sorts `keys` with `sorter`, and puts every column into the same order.
+///
+/// ```
+/// use chonky_structures::sorting::{permutation::sort_together, QuickSort};
+///
+/// let mut ages = vec![31, 25, 40];
+/// let mut names = vec!["ann", "bob", "cat"];
+/// let mut ids = [7, 8, 9];
+/// sort_together(&mut ages, &mut [&mut names, &mut ids], &QuickSort {});
+/// assert_eq!(ages, [25, 31, 40]);
+/// assert_eq!(names, ["bob", "ann", "cat"]);
+/// assert_eq!(ids, [8, 7, 9]);
+/// ```
+///
+/// # Panics
+///
+/// Panics if a column isn't as long as `keys`, before anything is moved.
+pub fn sort_together<K, S>(keys: &mut [K], columns: &mut [&mut dyn Reorder], sorter: &S)
+where
+    K: Ord,
+    S: Sorter<usize> + ?Sized,
+{
+    assert!(
+        columns.iter().all(|c| c.len() == keys.len()),
+        "columns have to be as long as the keys"
+    );
+    let order = argsort(keys, sorter);
+    let mut perm = order.clone();
+    apply_permutation_in_place(keys, &mut perm);
+    for column in columns {
+        perm.copy_from_slice(&order);
+        column.reorder(&mut perm);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorting::inputs::{self, Rng};
+    use crate::sorting::*;
+
+    #[test]
+    fn argsort_with_every_sorter() {
+        let list = inputs::few_unique(500, 20, 1);
+        let mut sorted = list.clone();
+        sorted.sort();
+
+        for entry in registry::entries() {
+            let order = argsort(&list, &*entry.build());
+            let by_order: Vec<_> = order.iter().map(|&i| list[i]).collect();
+            assert_eq!(by_order, sorted, "{}", entry.name);
+            if entry.properties.stable {
+                assert!(
+                    order
+                        .windows(2)
+                        .all(|w| list[w[0]] < list[w[1]] || w[0] < w[1]),
+                    "{}",
+                    entry.name
+                );
+            }
+
+            let mut moved = list.clone();
+            apply_permutation_in_place(&mut moved, &mut order.clone());
+            assert_eq!(moved, sorted);
+        }
+    }
+
+    #[test]
+    fn permutations() {
+        let mut perm: Vec<usize> = (0..100).collect();
+        Rng::new(4).shuffle(&mut perm);
+        let inverse = inverse_permutation(&perm);
+        assert!((0..100).all(|i| inverse[perm[i]] == i));
+
+        let mut list: Vec<usize> = (0..100).collect();
+        let mut scratch = perm.clone();
+        apply_permutation_in_place(&mut list, &mut scratch);
+        assert_eq!(list, perm);
+        assert!(scratch.iter().enumerate().all(|(i, p)| i == *p));
+        apply_permutation_in_place(&mut list, &mut inverse.clone());
+        assert!(list.iter().enumerate().all(|(i, x)| i == *x));
+    }
+
+    #[test]
+    #[should_panic(expected = "not a permutation")]
+    fn repeated_index() {
+        apply_permutation_in_place(&mut [1, 2, 3, 4], &mut [1, 2, 1, 0]);
+    }
+
+    #[test]
+    fn columns() {
+        let mut keys = inputs::random(50, 2);
+        let mut copy = keys.clone();
+        let mut deque: VecDeque<_> = (0..50).collect();
+        deque.rotate_left(10);
+        let mut expected: Vec<_> = keys.iter().copied().zip(deque.iter().copied()).collect();
+        expected.sort();
+
+        sort_together(&mut keys, &mut [&mut copy, &mut deque], &HeapSort);
+        assert_eq!(keys, copy);
+        let together: Vec<_> = keys.iter().copied().zip(deque.iter().copied()).collect();
+        assert_eq!(together, expected);
+    }
+}
@@ -1,48 +1,404 @@
-use super::Sorter;
+use core::cmp::Ordering;
 
+use super::instrument::{Observer, Probe};
+use super::{heap_sort, network, Complexity, Properties, Sorter};
+
+/// Pattern-defeating quicksort: an introsort that falls back to heap sort
+/// when pivots keep going bad, with special cases for runs of equal keys and
+/// inputs that are already (nearly) sorted.
 pub struct QuickSort {}
 
+/// Slices up to this length are sorted with a sorting network.
+pub(super) const MAX_NETWORK: usize = 20;
+/// Slices at least this long use the ninther to pick a pivot.
+const SHORTEST_NINTHER: usize = 50;
+/// If pivot selection needed more than this many swaps the slice is probably
+/// descending, so it gets reversed.
+const MAX_SWAPS: usize = 4 * 3;
+
 pub fn sort<T>(slice: &mut [T])
 where
     T: Ord,
 {
-    match slice.len() {
-        0 | 1 => return,
-        2 => {
-            if slice[0] > slice[1] {
-                slice.swap(0, 1);
+    sort_by(slice, T::cmp);
+}
+
+pub fn sort_by<T, F>(slice: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    if slice.len() <= 1 {
+        return;
+    }
+    let state = State::new(slice.len());
+    quick_sort(slice, &mut compare, None, state, &Probe::OFF);
+}
+
+/// What quick sort carries over from one partition of a slice to the next.
+#[derive(Copy, Clone)]
+pub(super) struct State {
+    /// How many more badly balanced partitions are allowed before giving up
+    /// on quicksort and switching to heap sort.
+    limit: u32,
+    was_balanced: bool,
+    was_partitioned: bool,
+}
+
+impl State {
+    pub(super) fn new(len: usize) -> Self {
+        State {
+            limit: usize::BITS - len.leading_zeros(),
+            was_balanced: true,
+            was_partitioned: true,
+        }
+    }
+
+    /// The state to start sorting one side of a partition with.
+    pub(super) fn fresh(self) -> Self {
+        State {
+            limit: self.limit,
+            was_balanced: true,
+            was_partitioned: true,
+        }
+    }
+}
+
+/// The outcome of one [`step`] of quick sort.
+pub(super) enum Step {
+    /// The slice is sorted.
+    Sorted,
+    /// `slice[..mid]` is equal to the predecessor and in place, and
+    /// `slice[mid..]` still needs sorting.
+    Skip(usize),
+    /// `slice` was partitioned around the pivot now at `mid`, and both sides
+    /// of it still need sorting.
+    Split(usize),
+}
+
+/// Sorts `slice`, where `pred` is the pivot of the partition step one level
+/// up, known to be no greater than any element of `slice`.
+pub(super) fn quick_sort<'a, T, F, O>(
+    mut slice: &'a mut [T],
+    compare: &mut F,
+    mut pred: Option<&'a T>,
+    mut state: State,
+    probe: &Probe<T, O>,
+) where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    let entered = probe.enter(slice);
+    loop {
+        match step(slice, compare, pred, &mut state, probe) {
+            Step::Sorted => break,
+            Step::Skip(mid) => slice = &mut slice[mid..],
+            Step::Split(mid) => {
+                let (left, right) = slice.split_at_mut(mid);
+                let (pivot, right) = right.split_first_mut().expect("pivot is in the slice");
+                let pivot = &*pivot;
+
+                // Recurse into the shorter side and loop on the longer one,
+                // so the recursion depth stays logarithmic.
+                if left.len() < right.len() {
+                    quick_sort(left, compare, pred, state.fresh(), probe);
+                    slice = right;
+                    pred = Some(pivot);
+                } else {
+                    quick_sort(right, compare, Some(pivot), state.fresh(), probe);
+                    slice = left;
+                }
             }
-            return;
         }
-        _ => {}
-    };
+    }
+    probe.leave(entered);
+}
+
+/// Either sorts `slice` outright, or splits it up into parts that still need
+/// sorting. `pred` is as for [`quick_sort`].
+pub(super) fn step<T, F, O>(
+    slice: &mut [T],
+    compare: &mut F,
+    pred: Option<&T>,
+    state: &mut State,
+    probe: &Probe<T, O>,
+) -> Step
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    let len = slice.len();
+    if len <= MAX_NETWORK {
+        network::sort_small(slice, compare, probe);
+        return Step::Sorted;
+    }
+
+    if state.limit == 0 {
+        heap_sort::heap_sort(slice, compare, probe);
+        return Step::Sorted;
+    }
+
+    if !state.was_balanced {
+        break_patterns(slice, probe);
+        state.limit -= 1;
+    }
+
+    let (pivot, likely_sorted) = choose_pivot(slice, compare, probe);
+
+    // The last partition was balanced and didn't move anything, and picking
+    // this pivot didn't either, so the slice is likely sorted already. Try
+    // to finish it off with a few insertions.
+    if state.was_balanced
+        && state.was_partitioned
+        && likely_sorted
+        && partial_insertion_sort(slice, compare, probe)
+    {
+        return Step::Sorted;
+    }
+
+    // The chosen pivot is equal to the predecessor, which is the smallest
+    // value in the slice. Split off everything equal to it, those are
+    // already in place.
+    if let Some(p) = pred {
+        if compare(p, &slice[pivot]) != Ordering::Less {
+            return Step::Skip(partition_equal(slice, pivot, compare, probe));
+        }
+    }
+
+    let (mid, partitioned) = partition(slice, pivot, compare, probe);
+    state.was_balanced = mid.min(len - mid) >= len / 8;
+    state.was_partitioned = partitioned;
+    Step::Split(mid)
+}
 
+/// Partitions `slice` around the element at `pivot` into the elements less
+/// than it followed by the rest, and returns the pivot's new index. Also
+/// returns whether the slice was partitioned already.
+pub(crate) fn partition<T, F, O>(
+    slice: &mut [T],
+    pivot: usize,
+    compare: &mut F,
+    probe: &Probe<T, O>,
+) -> (usize, bool)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    probe.swap(slice, 0, pivot);
     let (pivot, rest) = slice.split_first_mut().expect("slice cannot be empty");
+
     let mut left = 0;
-    let mut right = rest.len() - 1;
-    while right != 0 && left <= right {
-        if &rest[left] <= pivot {
+    let mut right = rest.len();
+    let mut was_partitioned = true;
+    loop {
+        while left < right && compare(&rest[left], pivot) == Ordering::Less {
             left += 1;
-        } else if &rest[right] > pivot {
+        }
+        while left < right && compare(&rest[right - 1], pivot) != Ordering::Less {
             right -= 1;
-        } else {
-            // element is on the wrong side
-            rest.swap(left, right);
+        }
+        if left >= right {
+            break;
+        }
+
+        // element is on the wrong side
+        was_partitioned = false;
+        right -= 1;
+        probe.swap(rest, left, right);
+        left += 1;
+    }
+
+    probe.swap(slice, 0, left);
+    (left, was_partitioned)
+}
+
+/// Moves every element equal to the one at `pivot` to the front of `slice`
+/// and returns how many there are. No element may be less than the pivot.
+pub(super) fn partition_equal<T, F, O>(
+    slice: &mut [T],
+    pivot: usize,
+    compare: &mut F,
+    probe: &Probe<T, O>,
+) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    probe.swap(slice, 0, pivot);
+    let (pivot, rest) = slice.split_first_mut().expect("slice cannot be empty");
+
+    let mut left = 0;
+    let mut right = rest.len();
+    loop {
+        while left < right && compare(pivot, &rest[left]) != Ordering::Less {
             left += 1;
+        }
+        while left < right && compare(pivot, &rest[right - 1]) == Ordering::Less {
             right -= 1;
         }
+        if left >= right {
+            break;
+        }
+
+        right -= 1;
+        probe.swap(rest, left, right);
+        left += 1;
+    }
+
+    // the pivot itself is still at the front
+    left + 1
+}
+
+/// Picks a pivot with the median of three, or the median of three medians
+/// on long slices. Returns its index and whether the slice looks sorted.
+pub(crate) fn choose_pivot<T, F, O>(
+    slice: &mut [T],
+    compare: &mut F,
+    probe: &Probe<T, O>,
+) -> (usize, bool)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    let len = slice.len();
+    let mut a = len / 4;
+    let mut b = len / 4 * 2;
+    let mut c = len / 4 * 3;
+    let mut swaps = 0;
+
+    if len >= 8 {
+        let mut sort2 = |a: &mut usize, b: &mut usize| {
+            if compare(&slice[*b], &slice[*a]) == Ordering::Less {
+                core::mem::swap(a, b);
+                swaps += 1;
+            }
+        };
+        let mut sort3 = |a: &mut usize, b: &mut usize, c: &mut usize| {
+            sort2(a, b);
+            sort2(b, c);
+            sort2(a, b);
+        };
+
+        if len >= SHORTEST_NINTHER {
+            let mut sort_adjacent = |i: &mut usize| {
+                let (mut lo, mut hi) = (*i - 1, *i + 1);
+                sort3(&mut lo, i, &mut hi);
+            };
+            sort_adjacent(&mut a);
+            sort_adjacent(&mut b);
+            sort_adjacent(&mut c);
+        }
+        sort3(&mut a, &mut b, &mut c);
+    }
+
+    if swaps < MAX_SWAPS {
+        (b, swaps == 0)
+    } else {
+        // everything was out of order, so the slice is likely descending
+        probe.reverse(slice);
+        (len - 1 - b, true)
+    }
+}
+
+/// Tries to sort a nearly sorted slice by fixing up a handful of out of
+/// order elements. Returns whether the slice ended up sorted.
+fn partial_insertion_sort<T, F, O>(slice: &mut [T], compare: &mut F, probe: &Probe<T, O>) -> bool
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    const MAX_STEPS: usize = 5;
+    const SHORTEST_SHIFTING: usize = 50;
+
+    let len = slice.len();
+    let mut i = 1;
+    for _ in 0..MAX_STEPS {
+        while i < len && compare(&slice[i], &slice[i - 1]) != Ordering::Less {
+            i += 1;
+        }
+        if i == len {
+            return true;
+        }
+        // not worth shifting elements around in a short slice
+        if len < SHORTEST_SHIFTING {
+            return false;
+        }
+
+        probe.swap(slice, i - 1, i);
+        // move the smaller element left and the larger one right until
+        // both are in place
+        let mut j = i - 1;
+        while j > 0 && compare(&slice[j], &slice[j - 1]) == Ordering::Less {
+            probe.swap(slice, j, j - 1);
+            j -= 1;
+        }
+        let mut j = i;
+        while j + 1 < len && compare(&slice[j + 1], &slice[j]) == Ordering::Less {
+            probe.swap(slice, j, j + 1);
+            j += 1;
+        }
     }
+    false
+}
 
-    // left was an index into rest, which is one less than the length of slice
-    let left = left + 1;
-    slice.swap(0, left - 1);
+/// Swaps a few elements around to break up patterns that keep producing
+/// unbalanced partitions.
+fn break_patterns<T, O>(slice: &mut [T], probe: &Probe<T, O>)
+where
+    O: Observer<T> + ?Sized,
+{
+    let len = slice.len();
+    // xorshift, seeded with the length so runs are reproducible
+    let mut random = len as u32;
+    let mut gen = || {
+        random ^= random << 13;
+        random ^= random >> 17;
+        random ^= random << 5;
+        random as usize
+    };
+    let modulus = len.next_power_of_two();
 
-    let (left, right) = slice.split_at_mut(left);
-    sort(left);
-    sort(right);
+    let pos = len / 4 * 2;
+    for i in 0..3 {
+        let mut other = gen() & (modulus - 1);
+        if other >= len {
+            other -= len;
+        }
+        probe.swap(slice, pos - 1 + i, other);
+    }
 }
 
+pub(super) const PROPERTIES: Properties = Properties {
+    stable: false,
+    in_place: true,
+    requires_clone: false,
+    best: Complexity::Linear,
+    average: Complexity::Linearithmic,
+    worst: Complexity::Linearithmic,
+};
+
 impl<T> Sorter<T> for QuickSort {
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+        sort_by(slice, compare);
+    }
+
+    fn properties(&self) -> Properties {
+        PROPERTIES
+    }
+
+    fn sort_observed(
+        &self,
+        slice: &mut [T],
+        compare: &mut dyn FnMut(&T, &T) -> Ordering,
+        observer: &dyn Observer<T>,
+    ) {
+        if slice.len() <= 1 {
+            return;
+        }
+        let probe = Probe::new(slice, observer);
+        let state = State::new(slice.len());
+        quick_sort(slice, &mut probe.observe(compare), None, state, &probe);
+    }
+
     fn sort(&self, slice: &mut [T])
     where
         T: Ord,
@@ -50,3 +406,50 @@ impl<T> Sorter<T> for QuickSort {
         sort(slice);
     }
 }
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec::Vec;
+
+    fn check(mut input: Vec<u64>) {
+        let mut expected = input.clone();
+        expected.sort();
+        super::sort(&mut input);
+        assert_eq!(input, expected);
+    }
+
+    #[test]
+    fn patterns() {
+        let n = 100_000;
+        check((0..n).collect());
+        check((0..n).rev().collect());
+        check((0..n).map(|i| i % 4).collect());
+        check((0..n).map(|i| i % 1000).collect());
+        check((0..n).map(|i| if i < n / 2 { i } else { n - i }).collect());
+        check((0..n).map(|i| (i * 2_654_435_761) % 1_000_003).collect());
+        check(vec_with_swaps(n));
+    }
+
+    fn vec_with_swaps(n: u64) -> Vec<u64> {
+        let mut v: Vec<_> = (0..n).collect();
+        v.swap(10, 500);
+        v.swap(7000, 99_000);
+        v
+    }
+
+    #[test]
+    fn comparisons_on_sorted_input_are_linear() {
+        for input in [
+            (0..10_000).collect::<Vec<u32>>(),
+            (0..10_000).rev().collect(),
+        ] {
+            let mut input = input;
+            let mut comparisons = 0;
+            super::sort_by(&mut input, |a, b| {
+                comparisons += 1;
+                a.cmp(b)
+            });
+            assert!(comparisons < 3 * input.len(), "{comparisons} comparisons");
+        }
+    }
+}
@@ -0,0 +1,526 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use super::{Complexity, Properties, RadixSorter};
+
+/// A key that can be taken apart into bytes for a radix sort.
+///
+/// Keys are ordered like their byte strings: byte by byte starting at the
+/// most significant one, with a key that runs out of bytes first sorting
+/// before any longer key sharing its prefix. Implement this for your own
+/// types by forwarding to the field they should be sorted by.
+pub trait RadixKey {
+    /// The number of bytes in every key of this type, or `None` if keys
+    /// vary in length.
+    const WIDTH: Option<usize>;
+
+    /// The number of bytes in this key.
+    fn key_len(&self) -> usize;
+
+    /// Byte `i` of this key, counting from the most significant. Only called
+    /// with `i < self.key_len()`.
+    fn key_byte(&self, i: usize) -> u8;
+}
+
+macro_rules! impl_radix_key_unsigned {
+    ($($ty:ty),*) => {
+        $(
+            impl RadixKey for $ty {
+                const WIDTH: Option<usize> = Some(core::mem::size_of::<$ty>());
+
+                fn key_len(&self) -> usize {
+                    core::mem::size_of::<$ty>()
+                }
+
+                fn key_byte(&self, i: usize) -> u8 {
+                    self.to_be_bytes()[i]
+                }
+            }
+        )*
+    };
+}
+
+impl_radix_key_unsigned!(u8, u16, u32, u64, u128, usize);
+
+macro_rules! impl_radix_key_signed {
+    ($($ty:ty => $unsigned:ty),*) => {
+        $(
+            impl RadixKey for $ty {
+                const WIDTH: Option<usize> = Some(core::mem::size_of::<$ty>());
+
+                fn key_len(&self) -> usize {
+                    core::mem::size_of::<$ty>()
+                }
+
+                fn key_byte(&self, i: usize) -> u8 {
+                    // flipping the sign bit puts negative numbers first
+                    ((*self as $unsigned) ^ (1 << (<$unsigned>::BITS - 1))).to_be_bytes()[i]
+                }
+            }
+        )*
+    };
+}
+
+impl_radix_key_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize);
+
+macro_rules! impl_radix_key_float {
+    ($($ty:ty => $unsigned:ty),*) => {
+        $(
+            /// Floats are ordered like [`total_cmp`](f64::total_cmp): negative
+            /// NaNs first, then negative infinity through `-0.0`, `0.0`
+            /// through infinity, and positive NaNs last.
+            impl RadixKey for $ty {
+                const WIDTH: Option<usize> = Some(core::mem::size_of::<$ty>());
+
+                fn key_len(&self) -> usize {
+                    core::mem::size_of::<$ty>()
+                }
+
+                fn key_byte(&self, i: usize) -> u8 {
+                    const SIGN: $unsigned = 1 << (<$unsigned>::BITS - 1);
+                    let bits = self.to_bits();
+                    // Positive floats already order like their bits, they
+                    // just need to go after the negative ones. Negative
+                    // floats order backwards, so flip all of their bits.
+                    let bits = if bits & SIGN == 0 { bits ^ SIGN } else { !bits };
+                    bits.to_be_bytes()[i]
+                }
+            }
+        )*
+    };
+}
+
+impl_radix_key_float!(f32 => u32, f64 => u64);
+
+impl RadixKey for bool {
+    const WIDTH: Option<usize> = Some(1);
+
+    fn key_len(&self) -> usize {
+        1
+    }
+
+    fn key_byte(&self, _: usize) -> u8 {
+        *self as u8
+    }
+}
+
+impl RadixKey for char {
+    const WIDTH: Option<usize> = Some(4);
+
+    fn key_len(&self) -> usize {
+        4
+    }
+
+    fn key_byte(&self, i: usize) -> u8 {
+        (*self as u32).to_be_bytes()[i]
+    }
+}
+
+impl<const N: usize> RadixKey for [u8; N] {
+    const WIDTH: Option<usize> = Some(N);
+
+    fn key_len(&self) -> usize {
+        N
+    }
+
+    fn key_byte(&self, i: usize) -> u8 {
+        self[i]
+    }
+}
+
+impl RadixKey for [u8] {
+    const WIDTH: Option<usize> = None;
+
+    fn key_len(&self) -> usize {
+        self.len()
+    }
+
+    fn key_byte(&self, i: usize) -> u8 {
+        self[i]
+    }
+}
+
+impl RadixKey for Vec<u8> {
+    const WIDTH: Option<usize> = None;
+
+    fn key_len(&self) -> usize {
+        self.len()
+    }
+
+    fn key_byte(&self, i: usize) -> u8 {
+        self[i]
+    }
+}
+
+/// Strings are ordered by their UTF-8 bytes, which is the same as ordering
+/// them by code point.
+impl RadixKey for str {
+    const WIDTH: Option<usize> = None;
+
+    fn key_len(&self) -> usize {
+        self.len()
+    }
+
+    fn key_byte(&self, i: usize) -> u8 {
+        self.as_bytes()[i]
+    }
+}
+
+impl RadixKey for String {
+    const WIDTH: Option<usize> = None;
+
+    fn key_len(&self) -> usize {
+        self.len()
+    }
+
+    fn key_byte(&self, i: usize) -> u8 {
+        self.as_bytes()[i]
+    }
+}
+
+impl<K> RadixKey for &K
+where
+    K: RadixKey + ?Sized,
+{
+    const WIDTH: Option<usize> = K::WIDTH;
+
+    fn key_len(&self) -> usize {
+        (**self).key_len()
+    }
+
+    fn key_byte(&self, i: usize) -> u8 {
+        (**self).key_byte(i)
+    }
+}
+
+/// The number of distinct digits: one for "the key has ended" plus one for
+/// every byte value.
+const DIGITS: usize = 257;
+
+/// Digit `level` of `key`, with 0 meaning the key is shorter than that and
+/// byte `b` mapped to `b + 1`.
+fn digit<K: RadixKey>(key: &K, level: usize) -> usize {
+    if level < key.key_len() {
+        key.key_byte(level) as usize + 1
+    } else {
+        0
+    }
+}
+
+/// Compares two elements digit by digit, starting at `level`.
+fn compare_from<T, D>(a: &T, b: &T, mut level: usize, digit_of: &mut D) -> Ordering
+where
+    D: FnMut(&T, usize) -> usize,
+{
+    loop {
+        let (da, db) = (digit_of(a, level), digit_of(b, level));
+        if da != db || da == 0 {
+            return da.cmp(&db);
+        }
+        level += 1;
+    }
+}
+
+/// Least significant digit first radix sort. Stable, and needs a buffer the
+/// size of the input.
+pub struct LsdRadixSort;
+
+/// Most significant digit first radix sort, done in place with American flag
+/// sort. Not stable, but moves elements with swaps only.
+pub struct MsdRadixSort;
+
+/// Buckets up to this size are insertion sorted by `MsdRadixSort`.
+const MSD_INSERTION: usize = 32;
+
+pub fn lsd_sort<T>(slice: &mut [T])
+where
+    T: RadixKey + Copy,
+{
+    lsd_sort_by_key(slice, |x| *x);
+}
+
+pub fn lsd_sort_by_key<T, K, F>(slice: &mut [T], mut f: F)
+where
+    T: Copy,
+    K: RadixKey,
+    F: FnMut(&T) -> K,
+{
+    let width = match K::WIDTH {
+        Some(width) => width,
+        None => slice.iter().map(|x| f(x).key_len()).max().unwrap_or(0),
+    };
+    if slice.len() < 2 || width == 0 {
+        return;
+    }
+
+    let mut buf = slice.to_vec();
+    let mut in_buf = false;
+    for level in (0..width).rev() {
+        let moved = if in_buf {
+            lsd_pass(&buf, slice, level, &mut f)
+        } else {
+            lsd_pass(slice, &mut buf, level, &mut f)
+        };
+        if moved {
+            in_buf = !in_buf;
+        }
+    }
+    if in_buf {
+        slice.copy_from_slice(&buf);
+    }
+}
+
+/// Distributes `src` into `dst` by digit `level`. Returns false without
+/// touching `dst` if every element has the same digit there.
+fn lsd_pass<T, K, F>(src: &[T], dst: &mut [T], level: usize, f: &mut F) -> bool
+where
+    T: Copy,
+    K: RadixKey,
+    F: FnMut(&T) -> K,
+{
+    let mut counts = [0; DIGITS];
+    for x in src {
+        counts[digit(&f(x), level)] += 1;
+    }
+    if counts.contains(&src.len()) {
+        return false;
+    }
+
+    let mut offsets = [0; DIGITS];
+    let mut sum = 0;
+    for (offset, count) in offsets.iter_mut().zip(counts) {
+        *offset = sum;
+        sum += count;
+    }
+    for x in src {
+        let d = digit(&f(x), level);
+        dst[offsets[d]] = *x;
+        offsets[d] += 1;
+    }
+    true
+}
+
+pub fn msd_sort<T>(slice: &mut [T])
+where
+    T: RadixKey,
+{
+    msd(slice, digit);
+}
+
+pub fn msd_sort_by_key<T, K, F>(slice: &mut [T], mut f: F)
+where
+    K: RadixKey,
+    F: FnMut(&T) -> K,
+{
+    msd(slice, |x, level| digit(&f(x), level));
+}
+
+/// American flag sort, with `digit_of` giving the digits of each element.
+fn msd<T, D>(slice: &mut [T], mut digit_of: D)
+where
+    D: FnMut(&T, usize) -> usize,
+{
+    // Ranges left to sort, and how many leading digits all keys in them
+    // share. Using a stack rather than recursion keeps long common prefixes
+    // from overflowing the call stack.
+    let mut stack = vec![(0, slice.len(), 0)];
+    while let Some((lo, hi, level)) = stack.pop() {
+        let part = &mut slice[lo..hi];
+        if part.len() <= MSD_INSERTION {
+            super::insertion_sort::sort_by(part, |a, b| compare_from(a, b, level, &mut digit_of));
+            continue;
+        }
+
+        let mut counts = [0; DIGITS];
+        for x in part.iter() {
+            counts[digit_of(x, level)] += 1;
+        }
+
+        let mut heads = [0; DIGITS];
+        let mut tails = [0; DIGITS];
+        let mut sum = 0;
+        for d in 0..DIGITS {
+            heads[d] = sum;
+            sum += counts[d];
+            tails[d] = sum;
+        }
+
+        // Walk each bucket, swapping every element that doesn't belong
+        // there into the next free spot of the bucket it does belong in.
+        for d in 0..DIGITS {
+            while heads[d] < tails[d] {
+                let home = digit_of(&part[heads[d]], level);
+                if home == d {
+                    heads[d] += 1;
+                } else {
+                    part.swap(heads[d], heads[home]);
+                    heads[home] += 1;
+                }
+            }
+        }
+
+        // Keys in bucket 0 have ended, so they're all equal
+        let mut start = lo + counts[0];
+        for count in &counts[1..] {
+            if *count > 1 {
+                stack.push((start, start + count, level + 1));
+            }
+            start += count;
+        }
+    }
+}
+
+impl<T> RadixSorter<T> for LsdRadixSort
+where
+    T: Copy,
+{
+    fn sort_by_key<K, F>(&self, slice: &mut [T], f: F)
+    where
+        K: RadixKey,
+        F: FnMut(&T) -> K,
+    {
+        lsd_sort_by_key(slice, f);
+    }
+
+    fn sort(&self, slice: &mut [T])
+    where
+        T: RadixKey,
+    {
+        lsd_sort(slice);
+    }
+
+    fn properties(&self) -> Properties {
+        Properties {
+            stable: true,
+            in_place: false,
+            requires_clone: true,
+            best: Complexity::Linear,
+            average: Complexity::Linear,
+            worst: Complexity::Linear,
+        }
+    }
+}
+
+impl<T> RadixSorter<T> for MsdRadixSort {
+    fn sort_by_key<K, F>(&self, slice: &mut [T], f: F)
+    where
+        K: RadixKey,
+        F: FnMut(&T) -> K,
+    {
+        msd_sort_by_key(slice, f);
+    }
+
+    fn sort(&self, slice: &mut [T])
+    where
+        T: RadixKey,
+    {
+        msd_sort(slice);
+    }
+
+    fn properties(&self) -> Properties {
+        Properties {
+            stable: false,
+            in_place: true,
+            requires_clone: false,
+            best: Complexity::Linear,
+            average: Complexity::Linear,
+            worst: Complexity::Linear,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LsdRadixSort, MsdRadixSort, RadixSorter};
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+
+    fn numbers() -> Vec<i64> {
+        (0..5000i64)
+            .map(|i| (i * 2_654_435_761) % 1_000_003 - 500_000)
+            .chain([i64::MIN, i64::MAX, 0, -1, 1])
+            .collect()
+    }
+
+    #[test]
+    fn integers() {
+        let mut expected = numbers();
+        expected.sort();
+
+        let mut lsd = numbers();
+        LsdRadixSort.sort(&mut lsd);
+        assert_eq!(lsd, expected);
+
+        let mut msd = numbers();
+        MsdRadixSort.sort(&mut msd);
+        assert_eq!(msd, expected);
+
+        let mut small: Vec<u32> = numbers().iter().map(|x| (*x as u32) % 300).collect();
+        let mut expected = small.clone();
+        expected.sort();
+        LsdRadixSort.sort(&mut small);
+        assert_eq!(small, expected);
+    }
+
+    #[test]
+    fn floats_follow_total_order() {
+        let input = [
+            3.5,
+            -0.0,
+            f64::NAN,
+            0.0,
+            -f64::NAN,
+            f64::INFINITY,
+            -2.0,
+            f64::MIN_POSITIVE,
+            f64::NEG_INFINITY,
+            -f64::MIN_POSITIVE,
+            1e300,
+        ];
+        let mut expected = input;
+        expected.sort_by(f64::total_cmp);
+        let bits = |v: &[f64]| v.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+
+        let mut lsd = input;
+        LsdRadixSort.sort(&mut lsd);
+        assert_eq!(bits(&lsd), bits(&expected));
+
+        let mut msd = input;
+        MsdRadixSort.sort(&mut msd);
+        assert_eq!(bits(&msd), bits(&expected));
+    }
+
+    #[test]
+    fn strings() {
+        let words: Vec<String> = (0..2000)
+            .map(|i: u32| (i * 7919 % 2003).to_string().repeat((i % 3 + 1) as usize))
+            .chain(["".into(), "a".into(), "ab".into(), "b".into(), "aa".into()])
+            .collect();
+        let mut expected = words.clone();
+        expected.sort();
+
+        let mut msd = words.clone();
+        MsdRadixSort.sort(&mut msd);
+        assert_eq!(msd, expected);
+
+        let mut lsd: Vec<&str> = words.iter().map(String::as_str).collect();
+        LsdRadixSort.sort(&mut lsd);
+        assert_eq!(lsd, expected);
+    }
+
+    #[test]
+    fn lsd_is_stable_by_key() {
+        let mut pairs: Vec<(u16, usize)> = (0..3000).map(|i| ((i * 31 % 97) as u16, i)).collect();
+        let mut expected = pairs.clone();
+        expected.sort_by_key(|p| p.0);
+        LsdRadixSort.sort_by_key(&mut pairs, |p| p.0);
+        assert_eq!(pairs, expected);
+
+        let mut msd = expected.clone();
+        msd.reverse();
+        MsdRadixSort.sort_by_key(&mut msd, |p| p.0);
+        assert!(msd.windows(2).all(|w| w[0].0 <= w[1].0));
+    }
+}
@@ -0,0 +1,145 @@
+//! Every [`Sorter`] by name, for picking an algorithm at runtime.
+//!
+//! ```
+//! use chonky_structures::sorting::registry;
+//!
+//! let sorter = registry::get::<u32>("heap").unwrap();
+//! let mut list = [3, 1, 2];
+//! sorter.sort(&mut list);
+//! assert_eq!(list, [1, 2, 3]);
+//! assert!(registry::get::<u32>("bogo").is_none());
+//! ```
+//!
+//! The registry includes the parallel sorters when the `std` feature is on,
+//! so the elements have to be `Send`.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use super::*;
+
+/// A sorter in the registry.
+pub struct Entry<T> {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub properties: Properties,
+    build: fn() -> Box<dyn Sorter<T>>,
+}
+
+/// A sorter's name, description and how to build it.
+type Row<T> = (&'static str, &'static str, fn() -> Box<dyn Sorter<T>>);
+
+impl<T> Entry<T> {
+    fn new((name, description, build): Row<T>) -> Self {
+        Entry {
+            name,
+            description,
+            properties: build().properties(),
+            build,
+        }
+    }
+
+    pub fn build(&self) -> Box<dyn Sorter<T>> {
+        (self.build)()
+    }
+}
+
+/// Every sorter, in alphabetical order of name.
+pub fn entries<T: Send>() -> Vec<Entry<T>> {
+    rows().into_iter().map(Entry::new).collect()
+}
+
+/// The sorter called `name`, if there is one.
+pub fn get<T: Send>(name: &str) -> Option<Box<dyn Sorter<T>>> {
+    rows()
+        .into_iter()
+        .find(|row| row.0 == name)
+        .map(|(_, _, build)| build())
+}
+
+/// The registry without building anything, so that looking up one sorter
+/// doesn't build the others to ask for their properties.
+fn rows<T: Send>() -> Vec<Row<T>> {
+    let mut rows = Vec::new();
+    rows.extend::<[Row<T>; 8]>([
+        ("auto", "picks one of the others for each slice", || {
+            Box::new(AutoSort)
+        }),
+        ("block-merge", "in-place stable block merge sort", || {
+            Box::new(BlockMergeSort)
+        }),
+        ("bottom-up-heap", "heap sort with Floyd's sift", || {
+            Box::new(BottomUpHeapSort)
+        }),
+        ("bottom-up-merge", "iterative merge sort", || {
+            Box::new(BottomUpMergeSort {})
+        }),
+        ("bubble", "bubble sort", || Box::new(BubbleSort)),
+        ("heap", "heap sort", || Box::new(HeapSort)),
+        ("insertion", "insertion sort", || Box::new(InsertionSort)),
+        ("merge", "top-down merge sort", || Box::new(MergeSort {})),
+    ]);
+    #[cfg(feature = "std")]
+    rows.extend::<[Row<T>; 2]>([
+        ("parallel-merge", "merge sort on every core", || {
+            Box::new(ParallelMergeSort::default())
+        }),
+        ("parallel-quick", "quicksort on every core", || {
+            Box::new(ParallelQuickSort::default())
+        }),
+    ]);
+    rows.extend::<[Row<T>; 10]>([
+        ("quick", "pattern-defeating quicksort", || {
+            Box::new(QuickSort {})
+        }),
+        ("selection", "selection sort", || Box::new(SelectionSort)),
+        ("shell", "Shell sort with Ciura's gaps", || {
+            Box::new(ShellSort {
+                gaps: GapSequence::Ciura,
+            })
+        }),
+        ("shell-knuth", "Shell sort with Knuth's gaps", || {
+            Box::new(ShellSort {
+                gaps: GapSequence::Knuth,
+            })
+        }),
+        (
+            "shell-sedgewick",
+            "Shell sort with Sedgewick's gaps",
+            || {
+                Box::new(ShellSort {
+                    gaps: GapSequence::Sedgewick,
+                })
+            },
+        ),
+        ("shell-shell", "Shell sort with Shell's gaps", || {
+            Box::new(ShellSort {
+                gaps: GapSequence::Shell,
+            })
+        }),
+        ("shell-tokuda", "Shell sort with Tokuda's gaps", || {
+            Box::new(ShellSort {
+                gaps: GapSequence::Tokuda,
+            })
+        }),
+        ("smooth", "Dijkstra's smoothsort", || Box::new(SmoothSort)),
+        ("tim", "natural merge sort", || Box::new(TimSort)),
+        ("weak-heap", "weak-heap sort", || Box::new(WeakHeapSort)),
+    ]);
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_sorted_and_unique() {
+        let entries = entries::<u8>();
+        assert!(entries.windows(2).all(|w| w[0].name < w[1].name));
+        for entry in &entries {
+            assert_eq!(entry.build().properties(), entry.properties);
+        }
+        assert!(get::<u8>("merge").unwrap().properties().stable);
+    }
+}
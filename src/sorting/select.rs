@@ -0,0 +1,263 @@
+//! Finding the smallest or largest few elements without sorting everything.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use super::instrument::Probe;
+use super::{heap_sort, insertion_sort, network, quick_sort};
+
+/// Reorders `slice` so the element at `k` is the one that would be there if
+/// it was sorted, with no greater elements before it and no smaller ones
+/// after it, and returns it.
+///
+/// Runs in linear time: quickselect, switching to the median of medians for
+/// pivots if too many partitions come out badly balanced.
+///
+/// # Panics
+///
+/// Panics if `k` is out of bounds.
+pub fn select_nth<T>(slice: &mut [T], k: usize) -> &mut T
+where
+    T: Ord,
+{
+    select_nth_by(slice, k, T::cmp)
+}
+
+pub fn select_nth_by<T, F>(slice: &mut [T], k: usize, mut compare: F) -> &mut T
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let len = slice.len();
+    assert!(k < len, "index {k} out of range for slice of length {len}");
+    select(slice, k, &mut compare, false);
+    &mut slice[k]
+}
+
+/// Moves the `k`th smallest element of `slice` into place. Pivots are picked
+/// with the median of medians from the start when `guaranteed` is set.
+fn select<T, F>(mut slice: &mut [T], mut k: usize, compare: &mut F, mut guaranteed: bool)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    // same as quick sort, allow log2(len) badly balanced partitions
+    let mut limit = usize::BITS - slice.len().leading_zeros();
+    // the last pivot to the left of `slice`, no greater than any element
+    // of it
+    let mut pred: Option<&T> = None;
+
+    loop {
+        let len = slice.len();
+        if len <= quick_sort::MAX_NETWORK {
+            network::sort_small(slice, compare, &Probe::OFF);
+            return;
+        }
+
+        let pivot = if guaranteed {
+            median_of_medians(slice, compare)
+        } else {
+            quick_sort::choose_pivot(slice, compare, &Probe::OFF).0
+        };
+
+        // The pivot is equal to the smallest value, split off everything
+        // equal to it, otherwise runs of equal keys only shrink the slice
+        // one element at a time.
+        if let Some(p) = pred {
+            if compare(p, &slice[pivot]) != Ordering::Less {
+                let mid = quick_sort::partition_equal(slice, pivot, compare, &Probe::OFF);
+                if k < mid {
+                    return;
+                }
+                slice = &mut slice[mid..];
+                k -= mid;
+                continue;
+            }
+        }
+
+        let (mid, _) = quick_sort::partition(slice, pivot, compare, &Probe::OFF);
+        if mid.min(len - mid) < len / 8 {
+            limit = limit.saturating_sub(1);
+            guaranteed |= limit == 0;
+        }
+
+        match k.cmp(&mid) {
+            Ordering::Equal => return,
+            Ordering::Less => slice = &mut slice[..mid],
+            Ordering::Greater => {
+                let (pivot, right) = slice[mid..]
+                    .split_first_mut()
+                    .expect("pivot is in the slice");
+                slice = right;
+                pred = Some(&*pivot);
+                k -= mid + 1;
+            }
+        }
+    }
+}
+
+/// Picks a pivot that has at least 3/10 of `slice` on either side of it, by
+/// taking the median of each group of five and then the median of those.
+fn median_of_medians<T, F>(slice: &mut [T], compare: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let groups = slice.len() / 5;
+    for i in 0..groups {
+        insertion_sort::sort_by(&mut slice[5 * i..5 * i + 5], &mut *compare);
+        // `i` is in a group that's been done already, or this one
+        slice.swap(i, 5 * i + 2);
+    }
+    select(&mut slice[..groups], groups / 2, compare, true);
+    groups / 2
+}
+
+/// Reorders `slice` so its first `k` elements are the smallest ones, in
+/// sorted order. The order of the rest is unspecified. Runs in O(n log k)
+/// time.
+pub fn partial_sort<T>(slice: &mut [T], k: usize)
+where
+    T: Ord,
+{
+    partial_sort_by(slice, k, T::cmp);
+}
+
+pub fn partial_sort_by<T, F>(slice: &mut [T], k: usize, mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let k = k.min(slice.len());
+    if k == 0 {
+        return;
+    }
+
+    // keep the smallest `k` seen so far in a max heap at the front, so the
+    // largest of them is the one to replace
+    let (heap, rest) = slice.split_at_mut(k);
+    heap_sort::build_heap(heap, &mut compare, &Probe::OFF);
+    for x in rest {
+        if compare(x, &heap[0]) == Ordering::Less {
+            core::mem::swap(x, &mut heap[0]);
+            heap_sort::heapify(heap, 0, &mut compare);
+        }
+    }
+    heap_sort::sort_heap(heap, &mut compare);
+}
+
+/// Returns the `k` largest items of `iter`, largest first, keeping only `k`
+/// of them in memory at once. Of equal items the earlier ones are kept, and
+/// come first.
+#[cfg(feature = "alloc")]
+pub fn top_k<I>(iter: I, k: usize) -> Vec<I::Item>
+where
+    I: IntoIterator,
+    I::Item: Ord,
+{
+    top_k_by(iter, k, I::Item::cmp)
+}
+
+#[cfg(feature = "alloc")]
+pub fn top_k_by<I, F>(iter: I, k: usize, mut compare: F) -> Vec<I::Item>
+where
+    I: IntoIterator,
+    F: FnMut(&I::Item, &I::Item) -> Ordering,
+{
+    // number the items so that of equal ones the earlier ranks higher
+    let mut iter = iter.into_iter().zip(0usize..);
+    let mut heap: Vec<_> = iter.by_ref().take(k).collect();
+    if heap.is_empty() {
+        return Vec::new();
+    }
+
+    // a min heap, so the lowest ranked of the `k` kept so far is on top
+    let mut reversed =
+        |a: &(I::Item, usize), b: &(I::Item, usize)| compare(&b.0, &a.0).then(a.1.cmp(&b.1));
+    heap_sort::build_heap(&mut heap, &mut reversed, &Probe::OFF);
+    for x in iter {
+        if reversed(&x, &heap[0]) == Ordering::Less {
+            heap[0] = x;
+            heap_sort::heapify(&mut heap, 0, &mut reversed);
+        }
+    }
+    heap_sort::sort_heap(&mut heap, &mut reversed);
+    heap.into_iter().map(|(x, _)| x).collect()
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec::Vec;
+
+    fn inputs(n: u64) -> Vec<Vec<u64>> {
+        Vec::from([
+            (0..n).collect(),
+            (0..n).rev().collect(),
+            (0..n).map(|i| i % 3).collect(),
+            (0..n).map(|_| 7).collect(),
+            (0..n).map(|i| (i * 2_654_435_761) % 1_000_003).collect(),
+            (0..n).map(|i| if i % 2 == 0 { i } else { n - i }).collect(),
+        ])
+    }
+
+    fn check_selected(list: &[u64], k: usize, sorted: &[u64]) {
+        assert_eq!(list[k], sorted[k]);
+        assert!(list[..k].iter().all(|x| *x <= list[k]));
+        assert!(list[k + 1..].iter().all(|x| *x >= list[k]));
+    }
+
+    #[test]
+    fn select_nth() {
+        for input in inputs(2000) {
+            let mut sorted = input.clone();
+            sorted.sort();
+            for k in [0, 1, 20, 999, 1000, 1998, 1999] {
+                let mut list = input.clone();
+                assert_eq!(*super::select_nth(&mut list, k), sorted[k]);
+                check_selected(&list, k, &sorted);
+
+                let mut list = input.clone();
+                super::select(&mut list, k, &mut u64::cmp, true);
+                check_selected(&list, k, &sorted);
+            }
+        }
+    }
+
+    #[test]
+    fn partial_sort() {
+        for input in inputs(1000) {
+            let mut sorted = input.clone();
+            sorted.sort();
+            for k in [0, 1, 10, 500, 1000, 2000] {
+                let mut list = input.clone();
+                super::partial_sort(&mut list, k);
+                let k = k.min(list.len());
+                assert_eq!(list[..k], sorted[..k]);
+                list.sort();
+                assert_eq!(list, sorted);
+            }
+        }
+    }
+
+    #[test]
+    fn top_k() {
+        let input: Vec<_> = (0..1000u64).map(|i| (i * 7919) % 1009).collect();
+        let mut sorted = input.clone();
+        sorted.sort_by(|a, b| b.cmp(a));
+        for k in [0, 1, 100, 1000, 5000] {
+            assert_eq!(
+                super::top_k(input.iter().copied(), k),
+                sorted[..k.min(1000)]
+            );
+        }
+
+        let pairs = [(1, 'a'), (3, 'b'), (2, 'c'), (3, 'd'), (3, 'e')];
+        let top = super::top_k_by(pairs, 2, |a, b| a.0.cmp(&b.0));
+        assert_eq!(top, [(3, 'b'), (3, 'd')]);
+
+        // a tie at the cut-off keeps the earlier item
+        let pairs = [(5, 'a'), (5, 'b'), (6, 'c')];
+        let top = super::top_k_by(pairs, 2, |a, b| a.0.cmp(&b.0));
+        assert_eq!(top, [(6, 'c'), (5, 'a')]);
+        let pairs = [(5, 'a'), (4, 'b'), (5, 'c'), (5, 'd'), (6, 'e')];
+        let top = super::top_k_by(pairs, 3, |a, b| a.0.cmp(&b.0));
+        assert_eq!(top, [(6, 'e'), (5, 'a'), (5, 'c')]);
+    }
+}
@@ -1,26 +1,69 @@
-use super::Sorter;
+use core::cmp::Ordering;
+
+use super::instrument::{Observer, Probe};
+use super::{Complexity, Properties, Sorter};
 
 pub struct SelectionSort;
 
 pub fn sort<T>(slice: &mut [T])
 where
     T: Ord,
+{
+    sort_by(slice, T::cmp);
+}
+
+pub fn sort_by<T, F>(slice: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    selection_sort(slice, &mut compare, &Probe::OFF);
+}
+
+fn selection_sort<T, F, O>(slice: &mut [T], compare: &mut F, probe: &Probe<T, O>)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
 {
     for i in 0..slice.len() {
         let mut smallest = i;
         for j in i..slice.len() {
-            if slice[smallest] > slice[j] {
+            if compare(&slice[smallest], &slice[j]) == Ordering::Greater {
                 smallest = j;
             }
         }
 
         if smallest != i {
-            slice.swap(smallest, i);
+            probe.swap(slice, smallest, i);
         }
     }
 }
 
 impl<T> Sorter<T> for SelectionSort {
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+        sort_by(slice, compare);
+    }
+
+    fn properties(&self) -> Properties {
+        Properties {
+            stable: false,
+            in_place: true,
+            requires_clone: false,
+            best: Complexity::Quadratic,
+            average: Complexity::Quadratic,
+            worst: Complexity::Quadratic,
+        }
+    }
+
+    fn sort_observed(
+        &self,
+        slice: &mut [T],
+        compare: &mut dyn FnMut(&T, &T) -> Ordering,
+        observer: &dyn Observer<T>,
+    ) {
+        let probe = Probe::new(slice, observer);
+        selection_sort(slice, &mut probe.observe(compare), &probe);
+    }
+
     fn sort(&self, slice: &mut [T])
     where
         T: Ord,
@@ -0,0 +1,204 @@
+use core::cmp::Ordering;
+
+use super::{Complexity, Properties, Sorter};
+
+/// Insertion sort over elements `gap` apart, for a shrinking series of gaps
+/// ending in 1. Sorts in place without allocating.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct ShellSort {
+    pub gaps: GapSequence,
+}
+
+/// The gaps a [`ShellSort`] goes through, largest first.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum GapSequence {
+    /// n/2, n/4, ..., 1 (Shell, 1959)
+    Shell,
+    /// 1, 4, 13, 40, ..., (3^k - 1) / 2 (Knuth, 1973)
+    Knuth,
+    /// 1, 8, 23, 77, ..., 4^k + 3 * 2^(k-1) + 1 (Sedgewick, 1986)
+    Sedgewick,
+    /// 1, 4, 10, 23, 57, 132, 301, 701, 1750, found empirically, continued
+    /// by multiplying by 2.25 (Ciura, 2001)
+    #[default]
+    Ciura,
+    /// 1, 4, 9, 20, 46, 103, ..., ceil(h_k) where h_k = 2.25 h_(k-1) + 1
+    /// (Tokuda, 1992)
+    Tokuda,
+}
+
+const CIURA: [usize; 9] = [1, 4, 10, 23, 57, 132, 301, 701, 1750];
+
+impl GapSequence {
+    pub const ALL: [GapSequence; 5] = [
+        GapSequence::Shell,
+        GapSequence::Knuth,
+        GapSequence::Sedgewick,
+        GapSequence::Ciura,
+        GapSequence::Tokuda,
+    ];
+
+    /// The `k`th smallest gap of the sequences that don't depend on the
+    /// length of the input.
+    fn nth(self, k: u32) -> usize {
+        match self {
+            GapSequence::Shell => unreachable!("shell's gaps depend on the input length"),
+            GapSequence::Knuth => (3usize.saturating_pow(k + 1) - 1) / 2,
+            GapSequence::Sedgewick => {
+                if k == 0 {
+                    1
+                } else {
+                    4usize
+                        .saturating_pow(k)
+                        .saturating_add(3 * 2usize.saturating_pow(k - 1))
+                        .saturating_add(1)
+                }
+            }
+            GapSequence::Ciura => match CIURA.get(k as usize) {
+                Some(gap) => *gap,
+                None => {
+                    let mut gap = CIURA[CIURA.len() - 1] as f64;
+                    for _ in CIURA.len()..=k as usize {
+                        gap *= 2.25;
+                    }
+                    gap as usize
+                }
+            },
+            GapSequence::Tokuda => {
+                let mut h = 1.0f64;
+                for _ in 0..k {
+                    h = 2.25 * h + 1.0;
+                }
+                // `f64::ceil` needs std
+                let gap = h as usize;
+                if (gap as f64) < h {
+                    gap + 1
+                } else {
+                    gap
+                }
+            }
+        }
+    }
+
+    /// The gaps to sort a slice of length `len` with, largest first.
+    pub fn gaps(self, len: usize) -> Gaps {
+        let next = match self {
+            GapSequence::Shell => len / 2,
+            _ => {
+                let mut k = 0;
+                while self.nth(k + 1) < len {
+                    k += 1;
+                }
+                k as usize
+            }
+        };
+        Gaps {
+            sequence: self,
+            next: if len > 1 { Some(next) } else { None },
+        }
+    }
+}
+
+/// Iterator over the gaps of a [`GapSequence`], largest first.
+pub struct Gaps {
+    sequence: GapSequence,
+    /// The next gap for `Shell`, and the index of the next gap otherwise.
+    next: Option<usize>,
+}
+
+impl Iterator for Gaps {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let next = self.next?;
+        match self.sequence {
+            GapSequence::Shell => {
+                self.next = if next > 1 { Some(next / 2) } else { None };
+                Some(next)
+            }
+            sequence => {
+                self.next = next.checked_sub(1);
+                Some(sequence.nth(next as u32))
+            }
+        }
+    }
+}
+
+pub fn sort<T>(slice: &mut [T], gaps: GapSequence)
+where
+    T: Ord,
+{
+    sort_by(slice, gaps, T::cmp);
+}
+
+pub fn sort_by<T, F>(slice: &mut [T], gaps: GapSequence, mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    for gap in gaps.gaps(slice.len()) {
+        for i in gap..slice.len() {
+            let mut j = i;
+            while j >= gap && compare(&slice[j], &slice[j - gap]) == Ordering::Less {
+                slice.swap(j, j - gap);
+                j -= gap;
+            }
+        }
+    }
+}
+
+impl<T> Sorter<T> for ShellSort {
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+        sort_by(slice, self.gaps, compare);
+    }
+
+    /// The bounds for Ciura's and Tokuda's sequences are only known
+    /// empirically, so their worst case is given as quadratic.
+    fn properties(&self) -> Properties {
+        let (average, worst) = match self.gaps {
+            GapSequence::Shell => (Complexity::ThreeHalves, Complexity::Quadratic),
+            GapSequence::Knuth => (Complexity::ThreeHalves, Complexity::ThreeHalves),
+            GapSequence::Sedgewick => (Complexity::FourThirds, Complexity::FourThirds),
+            GapSequence::Ciura | GapSequence::Tokuda => {
+                (Complexity::FourThirds, Complexity::Quadratic)
+            }
+        };
+        Properties {
+            stable: false,
+            in_place: true,
+            requires_clone: false,
+            best: Complexity::Linearithmic,
+            average,
+            worst,
+        }
+    }
+
+    fn sort(&self, slice: &mut [T])
+    where
+        T: Ord,
+    {
+        sort(slice, self.gaps);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::GapSequence;
+
+    #[test]
+    fn gap_sequences() {
+        let gaps = |seq: GapSequence, len| seq.gaps(len).collect::<alloc::vec::Vec<_>>();
+        assert_eq!(gaps(GapSequence::Shell, 100), [50, 25, 12, 6, 3, 1]);
+        assert_eq!(gaps(GapSequence::Knuth, 100), [40, 13, 4, 1]);
+        assert_eq!(gaps(GapSequence::Sedgewick, 100), [77, 23, 8, 1]);
+        assert_eq!(
+            gaps(GapSequence::Ciura, 5000),
+            [3937, 1750, 701, 301, 132, 57, 23, 10, 4, 1]
+        );
+        assert_eq!(gaps(GapSequence::Tokuda, 300), [233, 103, 46, 20, 9, 4, 1]);
+        for seq in GapSequence::ALL {
+            assert_eq!(gaps(seq, 0), []);
+            assert_eq!(gaps(seq, 1), []);
+            assert_eq!(gaps(seq, 2), [1]);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use core::cmp::Ordering;
+
+use super::instrument::{Observer, Probe};
+use super::{Complexity, Properties, Sorter};
+
+/// Dijkstra's smoothsort, a heap sort over a forest of Leonardo heaps that
+/// takes O(n) time on sorted input and degrades smoothly to O(n log n).
+pub struct SmoothSort;
+
+/// Enough Leonardo numbers for any slice length, the last ones saturate.
+const LEONARDO: [usize; 96] = {
+    let mut numbers = [1usize; 96];
+    let mut i = 2;
+    while i < numbers.len() {
+        numbers[i] = numbers[i - 1]
+            .saturating_add(numbers[i - 2])
+            .saturating_add(1);
+        i += 1;
+    }
+    numbers
+};
+
+pub fn sort<T>(slice: &mut [T])
+where
+    T: Ord,
+{
+    sort_by(slice, T::cmp);
+}
+
+pub fn sort_by<T, F>(slice: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    smooth_sort(slice, &mut compare, &Probe::OFF);
+}
+
+/// The slice is kept as a sequence of Leonardo heaps, heaps of
+/// `LEONARDO[k]` elements whose root is the last element and whose children
+/// are heaps of order `k - 1` and `k - 2`. The orders strictly decrease from
+/// left to right, apart from the last two which can be 1 and 0, and the
+/// roots are in ascending order so the last one is the largest element.
+/// `orders` has bit `k` set for each heap of order `k`.
+fn smooth_sort<T, F, O>(slice: &mut [T], compare: &mut F, probe: &Probe<T, O>)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    let len = slice.len();
+    let mut orders: u128 = 0;
+
+    for root in 0..len {
+        let smallest = orders.trailing_zeros();
+        if orders != 0 && orders & (1 << (smallest + 1)) != 0 {
+            // merge the last two heaps under the new root
+            orders &= !(0b11 << smallest);
+            orders |= 1 << (smallest + 2);
+        } else if smallest == 1 {
+            orders |= 1;
+        } else {
+            orders |= 0b10;
+        }
+        let order = orders.trailing_zeros();
+        trinkle(slice, root, order, orders & !(1 << order), compare, probe);
+    }
+
+    for root in (0..len).rev() {
+        let order = orders.trailing_zeros();
+        orders &= !(1 << order);
+        if order < 2 {
+            continue;
+        }
+
+        // split the heap into its children, and move their roots into place
+        let right = root - 1;
+        let left = right - LEONARDO[order as usize - 2];
+        trinkle(slice, left, order - 1, orders, compare, probe);
+        orders |= 1 << (order - 1);
+        trinkle(slice, right, order - 2, orders, compare, probe);
+        orders |= 1 << (order - 2);
+    }
+}
+
+/// Moves the root of the heap of `order` at `root`, whose children are
+/// heaps, into place among the roots of the heaps to its left, whose orders
+/// are the bits of `heaps`, and then sifts it down into whichever heap it
+/// ends up in.
+fn trinkle<T, F, O>(
+    slice: &mut [T],
+    mut root: usize,
+    mut order: u32,
+    mut heaps: u128,
+    compare: &mut F,
+    probe: &Probe<T, O>,
+) where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    while heaps != 0 {
+        let previous = root - LEONARDO[order as usize];
+        if compare(&slice[previous], &slice[root]) != Ordering::Greater {
+            break;
+        }
+        // the previous root can only come down if it's no less than this
+        // root's children, otherwise sifting fixes things
+        if order >= 2 {
+            let right = root - 1;
+            let left = right - LEONARDO[order as usize - 2];
+            if compare(&slice[previous], &slice[right]) != Ordering::Greater
+                || compare(&slice[previous], &slice[left]) != Ordering::Greater
+            {
+                break;
+            }
+        }
+        probe.swap(slice, previous, root);
+        root = previous;
+        order = heaps.trailing_zeros();
+        heaps &= heaps - 1;
+    }
+    sift(slice, root, order, compare, probe);
+}
+
+/// Sifts the root of the heap of `order` at `root` down into it.
+fn sift<T, F, O>(
+    slice: &mut [T],
+    mut root: usize,
+    mut order: u32,
+    compare: &mut F,
+    probe: &Probe<T, O>,
+) where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    while order >= 2 {
+        let right = root - 1;
+        let left = right - LEONARDO[order as usize - 2];
+        let (child, child_order) = if compare(&slice[left], &slice[right]) == Ordering::Greater {
+            (left, order - 1)
+        } else {
+            (right, order - 2)
+        };
+        if compare(&slice[root], &slice[child]) != Ordering::Less {
+            return;
+        }
+        probe.swap(slice, root, child);
+        root = child;
+        order = child_order;
+    }
+}
+
+impl<T> Sorter<T> for SmoothSort {
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+        sort_by(slice, compare);
+    }
+
+    fn properties(&self) -> Properties {
+        Properties {
+            stable: false,
+            in_place: true,
+            requires_clone: false,
+            best: Complexity::Linear,
+            average: Complexity::Linearithmic,
+            worst: Complexity::Linearithmic,
+        }
+    }
+
+    fn sort_observed(
+        &self,
+        slice: &mut [T],
+        compare: &mut dyn FnMut(&T, &T) -> Ordering,
+        observer: &dyn Observer<T>,
+    ) {
+        let probe = Probe::new(slice, observer);
+        smooth_sort(slice, &mut probe.observe(compare), &probe);
+    }
+
+    fn sort(&self, slice: &mut [T])
+    where
+        T: Ord,
+    {
+        sort(slice);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_length() {
+        // every shape of the forest of heaps up to a few hundred elements
+        let mut list = [0u32; 300];
+        for len in 0..list.len() {
+            let list = &mut list[..len];
+            let inputs: [fn(u32) -> u32; 4] = [
+                |i| i,
+                |i| 1000 - i,
+                |i| i.wrapping_mul(2_654_435_761) % 97,
+                |i| i % 3,
+            ];
+            for input in inputs {
+                for (i, x) in list.iter_mut().enumerate() {
+                    *x = input(i as u32);
+                }
+                sort(list);
+                assert!(list.windows(2).all(|w| w[0] <= w[1]), "{len}");
+            }
+        }
+    }
+}
@@ -1,23 +1,87 @@
 use super::*;
 
+/// Calls `f` with every sorter in the registry.
+#[cfg(feature = "alloc")]
+fn for_each_sorter<T: Send>(mut f: impl FnMut(&str, &dyn Sorter<T>)) {
+    for entry in registry::entries() {
+        f(entry.name, &*entry.build());
+    }
+}
+
+/// Calls `f` with every sorter there is without `alloc`, where there's no
+/// registry to list them.
+#[cfg(not(feature = "alloc"))]
+fn for_each_sorter<T: Ord>(mut f: impl FnMut(&str, &dyn Sorter<T>)) {
+    f("block-merge", &BlockMergeSort);
+    f("bottom-up-heap", &BottomUpHeapSort);
+    f("bubble", &BubbleSort);
+    f("heap", &HeapSort);
+    f("insertion", &InsertionSort);
+    f("quick", &QuickSort {});
+    f("selection", &SelectionSort);
+    for gaps in GapSequence::ALL {
+        f("shell", &ShellSort { gaps });
+    }
+    f("smooth", &SmoothSort);
+}
+
 #[test]
 fn test_all() {
     let list = [8, 4, 2, 17, 5, 3, 1, 13, 9, 6, 7, 12];
-    let fun = |sorter: &dyn Sorter<_>| {
-        let mut list = list.clone();
-        sorter.sort(&mut list);
-        list
-    };
-    macro_rules! test_sorter {
-        ($ty:ident) => {
-            let ret = fun(&$ty {});
-            assert_eq!(ret, [1, 2, 3, 4, 5, 6, 7, 8, 9, 12, 13, 17]);
-        };
+    for_each_sorter(|name, sorter| {
+        let mut ret = list;
+        sorter.sort(&mut ret);
+        assert_eq!(ret, [1, 2, 3, 4, 5, 6, 7, 8, 9, 12, 13, 17], "{name}");
+    });
+}
+
+#[test]
+fn test_comparators() {
+    let list = [8, 4, 2, 17, 5, 3, 1, 13, 9, 6, 7, 12];
+    for_each_sorter(|name, sorter| {
+        let mut ret = list;
+        Sorter::sort_by(&sorter, &mut ret, |a, b| b.cmp(a));
+        assert_eq!(ret, [17, 13, 12, 9, 8, 7, 6, 5, 4, 3, 2, 1], "{name}");
+
+        let mut ret = list;
+        Sorter::sort_by_key(&sorter, &mut ret, |x| (x % 3, *x));
+        assert_eq!(ret, [3, 6, 9, 12, 1, 4, 7, 13, 2, 5, 8, 17], "{name}");
+    });
+
+    // elements are (key, original index), so a stable sort must keep the
+    // indices of equal keys increasing
+    for_each_sorter(|name, sorter| {
+        let mut pairs = [(0, 0); 12];
+        for (i, pair) in pairs.iter_mut().enumerate() {
+            *pair = (list[i] % 4, i);
+        }
+        Sorter::sort_by_key(&sorter, &mut pairs, |p| p.0);
+        assert!(pairs.windows(2).all(|w| w[0].0 <= w[1].0), "{name}");
+        if sorter.properties().stable {
+            assert!(
+                pairs.windows(2).all(|w| w[0].0 < w[1].0 || w[0].1 < w[1].1),
+                "{name}"
+            );
+        }
+    });
+}
+
+/// `sort_by_cached_key` sorts the keys with the sorter itself, so it needs a
+/// sorter of a concrete type rather than one out of the registry.
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cached_key() {
+    fn check<S>(sorter: S)
+    where
+        S: Sorter<i32> + Sorter<((i32, i32), usize)>,
+    {
+        let mut ret = [8, 4, 2, 17, 5, 3, 1, 13, 9, 6, 7, 12];
+        sorter.sort_by_cached_key(&mut ret, |x| (x % 3, *x));
+        assert_eq!(ret, [3, 6, 9, 12, 1, 4, 7, 13, 2, 5, 8, 17]);
     }
-    test_sorter!(BubbleSort);
-    test_sorter!(HeapSort);
-    test_sorter!(QuickSort);
-    test_sorter!(InsertionSort);
-    test_sorter!(SelectionSort);
-    //test_sorter!(MergeSort);
+    check(HeapSort);
+    check(QuickSort {});
+    check(MergeSort {});
+    check(TimSort);
+    check(BlockMergeSort);
 }
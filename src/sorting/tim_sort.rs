@@ -0,0 +1,583 @@
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::mem::MaybeUninit;
+use core::{ptr, slice};
+
+use super::merge::MergeHole;
+use super::{Complexity, Properties, Sorter};
+
+/// Tim Peters' natural merge sort. Finds the runs already present in the
+/// input and merges them, so mostly sorted data sorts in close to linear
+/// time.
+pub struct TimSort;
+
+/// Inputs shorter than this are binary insertion sorted in one go.
+const MIN_MERGE: usize = 64;
+/// How many times in a row one run has to win before merging starts
+/// galloping.
+const MIN_GALLOP: usize = 7;
+
+#[derive(Copy, Clone)]
+struct Run {
+    start: usize,
+    len: usize,
+}
+
+/// Picks a run length between `MIN_MERGE / 2` and `MIN_MERGE` such that
+/// `len / minrun` is a power of two, or a little under one.
+fn min_run_length(mut len: usize) -> usize {
+    let mut r = 0;
+    while len >= MIN_MERGE {
+        r |= len & 1;
+        len >>= 1;
+    }
+    len + r
+}
+
+/// Returns the length of the run at the start of `slice`. A strictly
+/// descending run is reversed in place, strictness keeps it stable.
+fn count_run_and_make_ascending<T, F>(slice: &mut [T], compare: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let len = slice.len();
+    if len <= 1 {
+        return len;
+    }
+
+    let mut end = 2;
+    if compare(&slice[1], &slice[0]) == Ordering::Less {
+        while end < len && compare(&slice[end], &slice[end - 1]) == Ordering::Less {
+            end += 1;
+        }
+        slice[..end].reverse();
+    } else {
+        while end < len && compare(&slice[end], &slice[end - 1]) != Ordering::Less {
+            end += 1;
+        }
+    }
+    end
+}
+
+/// Sorts `slice`, of which the first `sorted` elements are already in order,
+/// by binary searching where each following element goes.
+fn binary_insertion_sort<T, F>(slice: &mut [T], sorted: usize, compare: &mut F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    for i in sorted.max(1)..slice.len() {
+        let (sorted, rest) = slice.split_at(i);
+        // after any equal elements, to keep it stable
+        let pos = sorted.partition_point(|x| compare(&rest[0], x) != Ordering::Less);
+        slice[pos..=i].rotate_right(1);
+    }
+}
+
+/// Returns the index of the first element of `slice` for which `before` is
+/// false. `before` must be true for some prefix of `slice` and false for the
+/// rest.
+///
+/// Searches outward from `hint` in exponentially growing steps before
+/// falling back to a binary search, which beats a plain binary search when
+/// the answer is close to `hint`.
+fn gallop<T>(slice: &[T], hint: usize, mut before: impl FnMut(&T) -> bool) -> usize {
+    let len = slice.len();
+    let mut last_ofs = 0;
+    let mut ofs = 1;
+
+    let (mut lo, mut hi) = if before(&slice[hint]) {
+        // the answer is in (hint + last_ofs, hint + ofs]
+        let max_ofs = len - hint;
+        while ofs < max_ofs && before(&slice[hint + ofs]) {
+            last_ofs = ofs;
+            ofs = ofs * 2 + 1;
+        }
+        (hint + last_ofs + 1, hint + ofs.min(max_ofs))
+    } else {
+        // the answer is in (hint - ofs, hint - last_ofs]
+        let max_ofs = hint + 1;
+        while ofs < max_ofs && !before(&slice[hint - ofs]) {
+            last_ofs = ofs;
+            ofs = ofs * 2 + 1;
+        }
+        (hint + 1 - ofs.min(max_ofs), hint - last_ofs)
+    };
+
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if before(&slice[mid]) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+/// Returns how many elements of `slice` are less than `key`.
+fn gallop_left<T, F>(key: &T, slice: &[T], hint: usize, compare: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    gallop(slice, hint, |x| compare(x, key) == Ordering::Less)
+}
+
+/// Returns how many elements of `slice` are less than or equal to `key`.
+fn gallop_right<T, F>(key: &T, slice: &[T], hint: usize, compare: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    gallop(slice, hint, |x| compare(key, x) != Ordering::Less)
+}
+
+/// Merges the runs `v[..len1]` and `v[len1..len1 + len2]` front to back,
+/// with the left run moved out into `buf`. `len1` should be the shorter run.
+///
+/// # Safety
+///
+/// `v` must be valid for `len1 + len2` elements, `buf` for `len1`, and the
+/// two must not overlap. Both runs must be non-empty, the last element of
+/// the left run must be greater than every element of the right run, and the
+/// first element of the right run less than every element of the left run.
+unsafe fn merge_lo<T, F>(
+    v: *mut T,
+    len1: usize,
+    len2: usize,
+    buf: *mut T,
+    compare: &mut F,
+    min_gallop: &mut usize,
+) where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    ptr::copy_nonoverlapping(v, buf, len1);
+    let mut hole = MergeHole {
+        start: buf,
+        end: buf.add(len1),
+        dest: v,
+    };
+    let mut right = v.add(len1);
+    let right_end = right.add(len2);
+
+    'merge: loop {
+        // how many times in a row each run supplied the next element
+        let mut count1 = 0;
+        let mut count2 = 0;
+
+        // one element at a time, until one run starts winning consistently
+        loop {
+            if compare(&*right, &*hole.start) == Ordering::Less {
+                ptr::copy_nonoverlapping(right, hole.dest, 1);
+                right = right.add(1);
+                count1 = 0;
+                count2 += 1;
+            } else {
+                ptr::copy_nonoverlapping(hole.start, hole.dest, 1);
+                hole.start = hole.start.add(1);
+                count1 += 1;
+                count2 = 0;
+            }
+            hole.dest = hole.dest.add(1);
+
+            if hole.start == hole.end || right == right_end {
+                break 'merge;
+            }
+            if count1.max(count2) >= *min_gallop {
+                break;
+            }
+        }
+
+        // gallop, copying whole stretches of one run at a time, until that
+        // stops paying off
+        loop {
+            let left = slice::from_raw_parts(hole.start, hole.end.offset_from(hole.start) as usize);
+            let count1 = gallop_right(&*right, left, 0, compare);
+            ptr::copy_nonoverlapping(hole.start, hole.dest, count1);
+            hole.start = hole.start.add(count1);
+            hole.dest = hole.dest.add(count1);
+            if hole.start == hole.end {
+                break 'merge;
+            }
+
+            ptr::copy_nonoverlapping(right, hole.dest, 1);
+            right = right.add(1);
+            hole.dest = hole.dest.add(1);
+            if right == right_end {
+                break 'merge;
+            }
+
+            let rest = slice::from_raw_parts(right, right_end.offset_from(right) as usize);
+            let count2 = gallop_left(&*hole.start, rest, 0, compare);
+            ptr::copy(right, hole.dest, count2);
+            right = right.add(count2);
+            hole.dest = hole.dest.add(count2);
+            if right == right_end {
+                break 'merge;
+            }
+
+            ptr::copy_nonoverlapping(hole.start, hole.dest, 1);
+            hole.start = hole.start.add(1);
+            hole.dest = hole.dest.add(1);
+            if hole.start == hole.end {
+                break 'merge;
+            }
+
+            *min_gallop = min_gallop.saturating_sub(1);
+            if count1 < MIN_GALLOP && count2 < MIN_GALLOP {
+                break;
+            }
+        }
+        // make it harder to get back into galloping mode
+        *min_gallop += 2;
+    }
+    // `hole` moves whatever is left of the left run into place
+}
+
+/// Merges the runs `v[..len1]` and `v[len1..len1 + len2]` back to front,
+/// with the right run moved out into `buf`. `len2` should be the shorter
+/// run. The mirror image of `merge_lo`, with the same safety requirements
+/// except that `buf` must be valid for `len2` elements.
+unsafe fn merge_hi<T, F>(
+    v: *mut T,
+    len1: usize,
+    len2: usize,
+    buf: *mut T,
+    compare: &mut F,
+    min_gallop: &mut usize,
+) where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    ptr::copy_nonoverlapping(v.add(len1), buf, len2);
+    // `hole.dest` is the end of what's left of the left run
+    let mut hole = MergeHole {
+        start: buf,
+        end: buf.add(len2),
+        dest: v.add(len1),
+    };
+    let mut out = v.add(len1 + len2);
+
+    'merge: loop {
+        let mut count1 = 0;
+        let mut count2 = 0;
+
+        loop {
+            out = out.sub(1);
+            if compare(&*hole.end.sub(1), &*hole.dest.sub(1)) == Ordering::Less {
+                hole.dest = hole.dest.sub(1);
+                ptr::copy_nonoverlapping(hole.dest, out, 1);
+                count1 += 1;
+                count2 = 0;
+            } else {
+                hole.end = hole.end.sub(1);
+                ptr::copy_nonoverlapping(hole.end, out, 1);
+                count1 = 0;
+                count2 += 1;
+            }
+
+            if hole.dest == v || hole.start == hole.end {
+                break 'merge;
+            }
+            if count1.max(count2) >= *min_gallop {
+                break;
+            }
+        }
+
+        loop {
+            let left = slice::from_raw_parts(v, hole.dest.offset_from(v) as usize);
+            let count1 =
+                left.len() - gallop_right(&*hole.end.sub(1), left, left.len() - 1, compare);
+            out = out.sub(count1);
+            hole.dest = hole.dest.sub(count1);
+            ptr::copy(hole.dest, out, count1);
+            if hole.dest == v {
+                break 'merge;
+            }
+
+            out = out.sub(1);
+            hole.end = hole.end.sub(1);
+            ptr::copy_nonoverlapping(hole.end, out, 1);
+            if hole.start == hole.end {
+                break 'merge;
+            }
+
+            let right =
+                slice::from_raw_parts(hole.start, hole.end.offset_from(hole.start) as usize);
+            let count2 =
+                right.len() - gallop_left(&*hole.dest.sub(1), right, right.len() - 1, compare);
+            out = out.sub(count2);
+            hole.end = hole.end.sub(count2);
+            ptr::copy_nonoverlapping(hole.end, out, count2);
+            if hole.start == hole.end {
+                break 'merge;
+            }
+
+            out = out.sub(1);
+            hole.dest = hole.dest.sub(1);
+            ptr::copy_nonoverlapping(hole.dest, out, 1);
+            if hole.dest == v {
+                break 'merge;
+            }
+
+            *min_gallop = min_gallop.saturating_sub(1);
+            if count1 < MIN_GALLOP && count2 < MIN_GALLOP {
+                break;
+            }
+        }
+        *min_gallop += 2;
+    }
+    // `hole` moves whatever is left of the right run into place
+}
+
+struct TimSortState<'a, T, F> {
+    slice: &'a mut [T],
+    compare: F,
+    buf: &'a mut [MaybeUninit<T>],
+    runs: Vec<Run>,
+    min_gallop: usize,
+}
+
+impl<T, F> TimSortState<'_, T, F>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    /// Merges the runs at `i` and `i + 1` on the stack.
+    fn merge_at(&mut self, i: usize) {
+        let mut a = self.runs[i];
+        let mut b = self.runs[i + 1];
+        self.runs[i].len += b.len;
+        self.runs.remove(i + 1);
+
+        // Elements at the start of `a` that are no greater than the first
+        // element of `b`, and at the end of `b` that are no less than the
+        // last element of `a`, are in place already.
+        let skip = gallop_right(
+            &self.slice[b.start],
+            &self.slice[a.start..a.start + a.len],
+            0,
+            &mut self.compare,
+        );
+        a.start += skip;
+        a.len -= skip;
+        if a.len == 0 {
+            return;
+        }
+        b.len = gallop_left(
+            &self.slice[a.start + a.len - 1],
+            &self.slice[b.start..b.start + b.len],
+            b.len - 1,
+            &mut self.compare,
+        );
+        if b.len == 0 {
+            return;
+        }
+
+        assert!(a.len.min(b.len) <= self.buf.len());
+        unsafe {
+            let v = self.slice.as_mut_ptr().add(a.start);
+            let buf = self.buf.as_mut_ptr() as *mut T;
+            if a.len <= b.len {
+                merge_lo(
+                    v,
+                    a.len,
+                    b.len,
+                    buf,
+                    &mut self.compare,
+                    &mut self.min_gallop,
+                );
+            } else {
+                merge_hi(
+                    v,
+                    a.len,
+                    b.len,
+                    buf,
+                    &mut self.compare,
+                    &mut self.min_gallop,
+                );
+            }
+        }
+    }
+
+    /// Merges runs until the lengths on the stack satisfy
+    ///
+    /// 1. `runs[n - 2].len > runs[n - 1].len + runs[n].len`
+    /// 2. `runs[n - 1].len > runs[n].len`
+    ///
+    /// for every `n`. Unlike the original description of the algorithm this
+    /// also checks the invariant one run further down the stack, which it
+    /// can otherwise break.
+    fn merge_collapse(&mut self) {
+        while self.runs.len() > 1 {
+            let len = |runs: &[Run], i: usize| runs[i].len;
+            let runs = &self.runs;
+            let mut n = runs.len() - 2;
+            if (n > 0 && len(runs, n - 1) <= len(runs, n) + len(runs, n + 1))
+                || (n > 1 && len(runs, n - 2) <= len(runs, n - 1) + len(runs, n))
+            {
+                if len(runs, n - 1) < len(runs, n + 1) {
+                    n -= 1;
+                }
+            } else if len(runs, n) > len(runs, n + 1) {
+                break;
+            }
+            self.merge_at(n);
+        }
+    }
+
+    /// Merges every run left on the stack.
+    fn merge_force_collapse(&mut self) {
+        while self.runs.len() > 1 {
+            let mut n = self.runs.len() - 2;
+            if n > 0 && self.runs[n - 1].len < self.runs[n + 1].len {
+                n -= 1;
+            }
+            self.merge_at(n);
+        }
+    }
+}
+
+pub fn sort<T>(slice: &mut [T])
+where
+    T: Ord,
+{
+    sort_by(slice, T::cmp);
+}
+
+pub fn sort_by<T, F>(slice: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let len = slice.len();
+    if len < 2 {
+        return;
+    }
+
+    if len < MIN_MERGE {
+        let run = count_run_and_make_ascending(slice, &mut compare);
+        binary_insertion_sort(slice, run, &mut compare);
+        return;
+    }
+
+    let mut buf = Vec::with_capacity(len / 2);
+    let mut state = TimSortState {
+        slice,
+        compare,
+        buf: buf.spare_capacity_mut(),
+        runs: Vec::new(),
+        min_gallop: MIN_GALLOP,
+    };
+
+    let min_run = min_run_length(len);
+    let mut start = 0;
+    while start < len {
+        let rest = &mut state.slice[start..];
+        let mut run = count_run_and_make_ascending(rest, &mut state.compare);
+
+        // extend short runs to `min_run`
+        if run < min_run {
+            let forced = min_run.min(rest.len());
+            binary_insertion_sort(&mut rest[..forced], run, &mut state.compare);
+            run = forced;
+        }
+
+        state.runs.push(Run { start, len: run });
+        state.merge_collapse();
+        start += run;
+    }
+    state.merge_force_collapse();
+}
+
+impl<T> Sorter<T> for TimSort {
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+        sort_by(slice, compare);
+    }
+
+    fn properties(&self) -> Properties {
+        Properties {
+            stable: true,
+            in_place: false,
+            requires_clone: false,
+            best: Complexity::Linear,
+            average: Complexity::Linearithmic,
+            worst: Complexity::Linearithmic,
+        }
+    }
+
+    fn sort(&self, slice: &mut [T])
+    where
+        T: Ord,
+    {
+        sort(slice);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    fn sort_counting(slice: &mut [(u32, usize)]) -> usize {
+        let mut comparisons = 0;
+        super::sort_by(slice, |a, b| {
+            comparisons += 1;
+            a.0.cmp(&b.0)
+        });
+        comparisons
+    }
+
+    fn check(keys: impl Iterator<Item = u32>) -> usize {
+        let mut pairs: Vec<_> = keys.zip(0..).collect();
+        let mut expected = pairs.clone();
+        expected.sort_by_key(|p| p.0);
+        let comparisons = sort_counting(&mut pairs);
+        assert_eq!(pairs, expected);
+        comparisons
+    }
+
+    #[test]
+    fn sorts_stably() {
+        check((0..10_000).map(|i| (i * 7919) % 1009));
+        check((0..10_000).map(|i| (i * 7919) % 7));
+        // sorted logs with a few shorter runs appended
+        check((0..5000).chain(100..900).chain(2000..2300).chain(0..50));
+        // long ascending runs, sawtooth
+        check((0..10_000).map(|i| i % 700));
+        // long descending runs
+        check((0..10_000).map(|i| 700 - i % 700));
+        // alternating long and short runs, to exercise `merge_collapse`
+        check((0..50).flat_map(|i| (0..(if i % 2 == 0 { 500 } else { 20 })).map(move |j| j * i)));
+    }
+
+    #[test]
+    fn linear_on_sorted_and_reversed_input() {
+        let n = 10_000;
+        assert_eq!(check(0..n as u32), n - 1);
+        assert_eq!(check((0..n as u32).rev()), n - 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn panicking_comparator_keeps_every_element() {
+        use alloc::boxed::Box;
+        use std::panic::{self, AssertUnwindSafe};
+
+        // enough elements and runs to get into galloping merges
+        let mut list: Vec<_> = (0..2000).map(|i| Box::new((i * 7) % 1000)).collect();
+        // panic late, while merging
+        let total = check((0..2000).map(|i| (i * 7) % 1000));
+        let mut calls = 0;
+        let ret = panic::catch_unwind(AssertUnwindSafe(|| {
+            super::sort_by(&mut list, |a, b| {
+                calls += 1;
+                if calls == total * 3 / 4 {
+                    panic!("comparator gave up");
+                }
+                a.cmp(b)
+            })
+        }));
+        assert!(ret.is_err());
+
+        let mut values: Vec<_> = list.iter().map(|b| **b).collect();
+        values.sort();
+        let mut expected: Vec<_> = (0..2000).map(|i| (i * 7) % 1000).collect();
+        expected.sort();
+        assert_eq!(values, expected);
+    }
+}
@@ -0,0 +1,369 @@
+//! Recording every step of a sort, and turning the recording into an
+//! animation.
+//!
+//! [`Trace::record`] sorts a slice with an [`Observer`] that writes down
+//! everything the sorter reports. The trace can then be replayed step by
+//! step, or exported as text frames with [`text`] or as an animated SVG
+//! with [`Svg`]. Only the sorters that report their moves (see
+//! [`instrument`](super::instrument)) can be replayed.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::cmp::Ordering;
+use core::fmt::{Display, Write};
+use core::ops::Range;
+
+use super::instrument::Observer;
+use super::Sorter;
+
+/// One step of a sort.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Event<T> {
+    Compare(Option<usize>, Option<usize>),
+    Swap(usize, usize),
+    Write(usize, T),
+    Enter(Range<usize>),
+    Leave(Range<usize>),
+}
+
+impl<T> Event<T> {
+    /// The positions the event is about.
+    fn highlights(&self) -> Range<usize> {
+        match self {
+            Event::Compare(a, b) => {
+                let (a, b) = (a.or(*b).unwrap_or(0), b.or(*a).unwrap_or(0));
+                a.min(b)..a.max(b) + 1
+            }
+            Event::Swap(i, j) => *i.min(j)..*i.max(j) + 1,
+            Event::Write(i, _) => *i..*i + 1,
+            Event::Enter(range) | Event::Leave(range) => range.clone(),
+        }
+    }
+
+    fn is_highlighted(&self, i: usize) -> bool {
+        match self {
+            Event::Compare(a, b) => *a == Some(i) || *b == Some(i),
+            Event::Swap(a, b) => *a == i || *b == i,
+            _ => self.highlights().contains(&i),
+        }
+    }
+}
+
+impl<T> Display for Event<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let index = |i: &Option<usize>| match i {
+            Some(i) => alloc::format!("{i}"),
+            None => String::from("_"),
+        };
+        match self {
+            Event::Compare(a, b) => write!(f, "compare {} {}", index(a), index(b)),
+            Event::Swap(i, j) => write!(f, "swap {i} {j}"),
+            Event::Write(i, _) => write!(f, "write {i}"),
+            Event::Enter(range) => write!(f, "enter {}..{}", range.start, range.end),
+            Event::Leave(range) => write!(f, "leave {}..{}", range.start, range.end),
+        }
+    }
+}
+
+/// Everything a sorter did to a slice, starting from `initial`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Trace<T> {
+    pub initial: Vec<T>,
+    pub events: Vec<Event<T>>,
+}
+
+struct Recorder<T> {
+    events: RefCell<Vec<Event<T>>>,
+}
+
+impl<T: Clone> Observer<T> for Recorder<T> {
+    fn compare(&self, a: Option<usize>, b: Option<usize>) {
+        self.events.borrow_mut().push(Event::Compare(a, b));
+    }
+
+    fn swap(&self, i: usize, j: usize) {
+        self.events.borrow_mut().push(Event::Swap(i, j));
+    }
+
+    fn write(&self, i: usize, value: &T) {
+        self.events
+            .borrow_mut()
+            .push(Event::Write(i, value.clone()));
+    }
+
+    fn enter(&self, range: Range<usize>) {
+        self.events.borrow_mut().push(Event::Enter(range));
+    }
+
+    fn leave(&self, range: Range<usize>) {
+        self.events.borrow_mut().push(Event::Leave(range));
+    }
+}
+
+impl<T: Clone> Trace<T> {
+    pub fn record<S>(sorter: &S, slice: &mut [T]) -> Self
+    where
+        T: Ord,
+        S: Sorter<T> + ?Sized,
+    {
+        Self::record_by(sorter, slice, T::cmp)
+    }
+
+    /// Sorts `slice` with `sorter`, recording every step.
+    pub fn record_by<S, F>(sorter: &S, slice: &mut [T], mut compare: F) -> Self
+    where
+        S: Sorter<T> + ?Sized,
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let initial = slice.to_vec();
+        let recorder = Recorder {
+            events: RefCell::new(Vec::new()),
+        };
+        sorter.sort_observed(slice, &mut compare, &recorder);
+        Trace {
+            initial,
+            events: recorder.events.into_inner(),
+        }
+    }
+
+    /// Calls `f` with each event and the state of the slice after it.
+    pub fn replay<F>(&self, mut f: F)
+    where
+        F: FnMut(&Event<T>, &[T]),
+    {
+        let mut state = self.initial.clone();
+        for event in &self.events {
+            match event {
+                Event::Swap(i, j) => state.swap(*i, *j),
+                Event::Write(i, value) => state[*i] = value.clone(),
+                _ => {}
+            }
+            f(event, &state);
+        }
+    }
+}
+
+/// Renders `trace` as text, one frame per event: the event, and then the
+/// slice after it with the positions it's about in brackets.
+///
+/// ```
+/// use chonky_structures::sorting::{trace, trace::Trace, BubbleSort};
+///
+/// let trace = Trace::record(&BubbleSort, &mut [2, 1]);
+/// assert_eq!(
+///     trace::text(&trace),
+///     "compare 1 0\n[2] [1]\n\nswap 1 0\n[1] [2]\n\ncompare 1 0\n[1] [2]\n"
+/// );
+/// ```
+pub fn text<T>(trace: &Trace<T>) -> String
+where
+    T: Clone + Display,
+{
+    let mut out = String::new();
+    trace.replay(|event, state| {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        let _ = writeln!(out, "{event}");
+        for (i, x) in state.iter().enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            let _ = if event.is_highlighted(i) {
+                write!(out, "[{x}]")
+            } else {
+                write!(out, "{x}")
+            };
+        }
+        out.push('\n');
+    });
+    out
+}
+
+/// Renders a trace as an animated SVG of bars, one per element, with the
+/// bars each event is about highlighted.
+#[derive(Copy, Clone, Debug)]
+pub struct Svg {
+    pub width: f64,
+    pub height: f64,
+    /// How long each event is shown for, in seconds.
+    pub frame_time: f64,
+}
+
+impl Default for Svg {
+    fn default() -> Self {
+        Svg {
+            width: 640.0,
+            height: 360.0,
+            frame_time: 0.05,
+        }
+    }
+}
+
+const BAR: &str = "#4a6fa5";
+const COMPARED: &str = "#e8a33d";
+const MOVED: &str = "#d1495b";
+const RANGE: &str = "#8fb8de";
+
+impl Svg {
+    pub fn render<T>(&self, trace: &Trace<T>) -> String
+    where
+        T: Copy + Into<f64>,
+    {
+        let n = trace.initial.len();
+        let low = trace
+            .initial
+            .iter()
+            .map(|x| (*x).into())
+            .fold(0.0, f64::min);
+        let high = trace
+            .initial
+            .iter()
+            .map(|x| (*x).into())
+            .fold(0.0, f64::max);
+        let scale = if high > low {
+            self.height / (high - low)
+        } else {
+            0.0
+        };
+        let bar_width = self.width / n.max(1) as f64;
+
+        // the height and colour of every bar in every frame, starting with
+        // the unsorted slice
+        let mut heights: Vec<Vec<f64>> = Vec::from_iter((0..n).map(|_| Vec::new()));
+        let mut colours: Vec<Vec<&str>> = Vec::from_iter((0..n).map(|_| Vec::new()));
+        let mut push = |state: &[T], colour: &dyn Fn(usize) -> &'static str| {
+            for (i, x) in state.iter().enumerate() {
+                heights[i].push(((*x).into() - low) * scale);
+                colours[i].push(colour(i));
+            }
+        };
+        push(&trace.initial, &|_| BAR);
+        trace.replay(|event, state| {
+            let colour = match event {
+                Event::Compare(..) => COMPARED,
+                Event::Swap(..) | Event::Write(..) => MOVED,
+                Event::Enter(..) | Event::Leave(..) => RANGE,
+            };
+            push(state, &|i| {
+                if event.is_highlighted(i) {
+                    colour
+                } else {
+                    BAR
+                }
+            });
+        });
+
+        let frames = trace.events.len() + 1;
+        let duration = frames as f64 * self.frame_time;
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = self.width,
+            h = self.height,
+        );
+        let join = |values: &mut dyn Iterator<Item = f64>| {
+            let mut joined = String::new();
+            for (k, value) in values.enumerate() {
+                let separator = if k == 0 { "" } else { ";" };
+                let _ = write!(joined, "{separator}{value:.1}");
+            }
+            joined
+        };
+        let animate = |attribute: &str, values: &str| {
+            alloc::format!(
+                r#"<animate attributeName="{attribute}" values="{values}" dur="{duration}s" calcMode="discrete" fill="freeze"/>"#
+            )
+        };
+        for i in 0..n {
+            let bar = &heights[i];
+            let _ = writeln!(
+                out,
+                r#"<rect x="{x:.1}" width="{w:.1}" y="{y:.1}" height="{h:.1}" fill="{BAR}">{}{}{}</rect>"#,
+                animate("height", &join(&mut bar.iter().copied())),
+                animate("y", &join(&mut bar.iter().map(|h| self.height - h))),
+                animate("fill", &colours[i].join(";")),
+                x = i as f64 * bar_width,
+                w = bar_width * 0.9,
+                y = self.height - bar[0],
+                h = bar[0],
+            );
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, Svg, Trace};
+    use crate::sorting::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn replays_to_the_sorted_slice() {
+        let input: Vec<u32> = (0..200).map(|i| (i * 7919) % 211).collect();
+        let mut expected = input.clone();
+        expected.sort();
+
+        for entry in registry::entries() {
+            let sorter = entry.build();
+            let mut list = input.clone();
+            let trace = Trace::record(&*sorter, &mut list);
+            assert_eq!(list, expected, "{}", entry.name);
+
+            // sorters that don't observe their steps only report comparisons
+            let compares_only = trace.events.iter().all(|e| matches!(e, Event::Compare(..)));
+            if compares_only {
+                continue;
+            }
+            let mut last = Vec::new();
+            trace.replay(|_, state| last = state.to_vec());
+            assert_eq!(last, expected, "{}", entry.name);
+        }
+    }
+
+    #[test]
+    fn merge_sort_events() {
+        let mut list = [4, 3, 2, 1];
+        let trace = Trace::record(&MergeSort {}, &mut list);
+        // short enough to be insertion sorted, without recursing
+        assert_eq!(trace.events.len(), 12);
+        assert!(trace
+            .events
+            .iter()
+            .all(|e| matches!(e, Event::Compare(..) | Event::Swap(..))));
+
+        let mut list: Vec<_> = (0..40).rev().collect();
+        let trace = Trace::record(&MergeSort {}, &mut list);
+        assert_eq!(trace.events.first(), Some(&Event::Enter(0..40)));
+        assert_eq!(trace.events.last(), Some(&Event::Leave(0..40)));
+        assert!(trace.events.contains(&Event::Write(0, 0)));
+    }
+
+    #[test]
+    fn svg_has_a_bar_per_element() {
+        let trace = Trace::record(&InsertionSort, &mut [3u8, 1, 2]);
+        let svg = Svg::default().render(&trace);
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<rect").count(), 3);
+        // one frame for the start and one per event
+        let frames = trace.events.len() + 1;
+        let heights = svg
+            .split(r#"attributeName="height" values=""#)
+            .nth(1)
+            .unwrap();
+        let heights = heights.split('"').next().unwrap();
+        assert_eq!(heights.split(';').count(), frames);
+
+        // every bar stands on the bottom edge
+        let ys = svg.split(r#"attributeName="y" values=""#).nth(1).unwrap();
+        let ys = ys.split('"').next().unwrap();
+        for (h, y) in heights.split(';').zip(ys.split(';')) {
+            let (h, y): (f64, f64) = (h.parse().unwrap(), y.parse().unwrap());
+            assert!((h + y - Svg::default().height).abs() < 0.11, "{h} {y}");
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use super::instrument::{Observer, Probe};
+use super::{Complexity, Properties, Sorter};
+
+/// Dutton's weak-heap sort, which needs at most n log n + 0.1n comparisons,
+/// fewer than any other heap sort, at the cost of a bit per element.
+pub struct WeakHeapSort;
+
+/// A weak heap only orders each node before its right subtree. Swapping a
+/// node's subtrees is done by flipping its bit, so `2i + bit(i)` is the left
+/// child of `i` and `2i + 1 - bit(i)` the right.
+struct Bits(Vec<u64>);
+
+impl Bits {
+    fn get(&self, i: usize) -> usize {
+        (self.0[i / 64] >> (i % 64)) as usize & 1
+    }
+
+    fn flip(&mut self, i: usize) {
+        self.0[i / 64] ^= 1 << (i % 64);
+    }
+}
+
+pub fn sort<T>(slice: &mut [T])
+where
+    T: Ord,
+{
+    sort_by(slice, T::cmp);
+}
+
+pub fn sort_by<T, F>(slice: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    weak_heap_sort(slice, &mut compare, &Probe::OFF);
+}
+
+fn weak_heap_sort<T, F, O>(slice: &mut [T], compare: &mut F, probe: &Probe<T, O>)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    O: Observer<T> + ?Sized,
+{
+    let len = slice.len();
+    if len < 2 {
+        return;
+    }
+    let mut bits = Bits(vec![0; len.div_ceil(64)]);
+
+    // Makes the larger of `slice[i]` and `slice[j]`, where `i` is the
+    // distinguished ancestor of `j`, the ancestor.
+    let mut join = |slice: &mut [T], bits: &mut Bits, i: usize, j: usize| {
+        if compare(&slice[i], &slice[j]) == Ordering::Less {
+            probe.swap(slice, i, j);
+            bits.flip(j);
+        }
+    };
+
+    for j in (1..len).rev() {
+        // the distinguished ancestor is the parent of the first node up
+        // from `j` that is a right child
+        let mut i = j;
+        while i & 1 == bits.get(i / 2) {
+            i /= 2;
+        }
+        join(slice, &mut bits, i / 2, j);
+    }
+
+    for end in (2..len).rev() {
+        probe.swap(slice, 0, end);
+        // the new root is compared with the nodes on the leftmost path of
+        // the root's only child, from the bottom up
+        let mut x = 1;
+        while 2 * x + bits.get(x) < end {
+            x = 2 * x + bits.get(x);
+        }
+        while x > 0 {
+            join(&mut slice[..end], &mut bits, 0, x);
+            x /= 2;
+        }
+    }
+    probe.swap(slice, 0, 1);
+}
+
+impl<T> Sorter<T> for WeakHeapSort {
+    fn sort_by_dyn(&self, slice: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+        sort_by(slice, compare);
+    }
+
+    fn properties(&self) -> Properties {
+        Properties {
+            stable: false,
+            in_place: false,
+            requires_clone: false,
+            best: Complexity::Linearithmic,
+            average: Complexity::Linearithmic,
+            worst: Complexity::Linearithmic,
+        }
+    }
+
+    fn sort_observed(
+        &self,
+        slice: &mut [T],
+        compare: &mut dyn FnMut(&T, &T) -> Ordering,
+        observer: &dyn Observer<T>,
+    ) {
+        let probe = Probe::new(slice, observer);
+        weak_heap_sort(slice, &mut probe.observe(compare), &probe);
+    }
+
+    fn sort(&self, slice: &mut [T])
+    where
+        T: Ord,
+    {
+        sort(slice);
+    }
+}
@@ -0,0 +1,30 @@
+//! Counts the allocations sorters make. This needs [`CountingAllocator`] as
+//! the global allocator, so it runs in its own binary rather than with the
+//! unit tests.
+
+use chonky_structures::sorting::inputs;
+use chonky_structures::sorting::instrument::{CountingAllocator, Instrumented};
+use chonky_structures::sorting::*;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator::new(std::alloc::System);
+
+#[test]
+fn merge_sort_allocates_one_buffer() {
+    let stats = Instrumented::new(MergeSort {}).sort(&mut inputs::random(10_000, 1));
+    assert_eq!(stats.allocations, 1);
+    assert!(stats.allocated_bytes >= 5_000 * 8);
+}
+
+#[test]
+fn in_place_sorters_allocate_nothing() {
+    for entry in registry::entries::<u64>() {
+        // the parallel sorters allocate to start their threads
+        if !entry.properties.in_place || entry.name.starts_with("parallel") {
+            continue;
+        }
+        let sorter = entry.build();
+        let stats = Instrumented::new(&*sorter).sort(&mut inputs::random(2_000, 2));
+        assert_eq!(stats.allocations, 0, "{}", entry.name);
+    }
+}